@@ -0,0 +1,177 @@
+use std::{
+    io::{self, Write},
+    process::Command,
+};
+
+use crate::terminal;
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Open,
+    Copy,
+    Print,
+    Exec,
+}
+
+impl Action {
+    pub fn parse<S: AsRef<str>>(input: S) -> Option<(Action, usize)> {
+        let command = input.as_ref().trim().strip_prefix(':')?;
+
+        let (action, index) = match command.chars().next()? {
+            'e' => (Action::Open, &command[1..]),
+            'y' => (Action::Copy, &command[1..]),
+            'p' => (Action::Print, &command[1..]),
+            'x' => (Action::Exec, &command[1..]),
+            _ => (Action::Open, command),
+        };
+
+        match index.trim().parse::<usize>() {
+            Ok(index) if index > 0 => Some((action, index)),
+            _ => None,
+        }
+    }
+
+    pub fn run<S: AsRef<str>>(&self, path: S, exec: &Option<String>) -> io::Result<()> {
+        let path = path.as_ref();
+
+        match self {
+            Action::Open => Self::open_in_editor(path),
+            Action::Copy => {
+                let mut tty = terminal::tty();
+                tty.write_all(osc52(path).as_bytes())?;
+                tty.flush()
+            }
+            Action::Print => {
+                println!("{}", path);
+                Ok(())
+            }
+            Action::Exec => match exec {
+                Some(template) => Self::shell(&fill_template(template, path)),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no command template, pass --exec=<command {}>",
+                )),
+            },
+        }
+    }
+
+    fn open_in_editor(path: &str) -> io::Result<()> {
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "$EDITOR is not set"))?;
+
+        let mut words = editor.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "$EDITOR is empty"))?;
+
+        Command::new(program)
+            .args(words)
+            .arg("+1")
+            .arg(path)
+            .status()
+            .map(|_| ())
+    }
+
+    #[cfg(windows)]
+    fn shell(command: &str) -> io::Result<()> {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(command)
+            .status()
+            .map(|_| ())
+    }
+
+    #[cfg(not(windows))]
+    fn shell(command: &str) -> io::Result<()> {
+        Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .status()
+            .map(|_| ())
+    }
+}
+
+#[cfg(windows)]
+fn quote(path: &str) -> String {
+    format!("\"{}\"", path)
+}
+
+#[cfg(not(windows))]
+fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+pub fn fill_template<S: AsRef<str>>(template: &str, path: S) -> String {
+    let path = quote(path.as_ref());
+
+    if template.contains("{}") {
+        template.replace("{}", &path)
+    } else {
+        format!("{} {}", template, path)
+    }
+}
+
+pub fn osc52<S: AsRef<str>>(text: S) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_ref().as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(TABLE[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod action_tests {
+    use super::{base64, fill_template, osc52, Action};
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Action::parse(":3"), Some((Action::Open, 3)));
+        assert_eq!(Action::parse(" :e 12 "), Some((Action::Open, 12)));
+        assert_eq!(Action::parse(":y1"), Some((Action::Copy, 1)));
+        assert_eq!(Action::parse(":p 2"), Some((Action::Print, 2)));
+        assert_eq!(Action::parse(":x 4"), Some((Action::Exec, 4)));
+        assert_eq!(Action::parse(":0"), None);
+        assert_eq!(Action::parse(":q"), None);
+        assert_eq!(Action::parse("3"), None);
+        assert_eq!(Action::parse("main.rs"), None);
+    }
+
+    #[test]
+    fn encode_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"./src/main.rs"), "Li9zcmMvbWFpbi5ycw==");
+        assert_eq!(osc52("foo"), "\x1b]52;c;Zm9v\x07");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn fill_command_template() {
+        assert_eq!(fill_template("wc -l {}", "./a b.rs"), "wc -l './a b.rs'");
+        assert_eq!(fill_template("cat", "./it's"), r"cat './it'\''s'");
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crossterm::style::Color;
+use serde::Deserialize;
+
+use crate::diagnostics;
+
+pub const PROJECT_FILE: &str = ".rfind.toml";
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub flags: Vec<String>,
+    pub ignore: Vec<String>,
+    pub colors: ColorNames,
+    pub types: HashMap<String, Vec<String>>,
+    pub profiles: HashMap<String, Profile>,
+}
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub flags: Vec<String>,
+    pub ignore: Vec<String>,
+}
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorNames {
+    pub index: Option<String>,
+    pub path: Option<String>,
+    pub matched: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorScheme {
+    pub index: Color,
+    pub path: Color,
+    pub matched: Color,
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        ColorScheme {
+            index: Color::DarkGrey,
+            path: Color::Reset,
+            matched: Color::Red,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Config {
+        let mut config = Config::default();
+
+        if let Some(path) = Self::user_path() {
+            config.merge(Self::from_file(path));
+        }
+
+        if let Some(path) = std::env::current_dir()
+            .ok()
+            .and_then(|dir| Self::project_path(&dir))
+        {
+            config.merge(Self::from_file(path));
+        }
+
+        config
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Config {
+        let text = match std::fs::read_to_string(path.as_ref()) {
+            Ok(text) => text,
+            Err(_) => return Config::default(),
+        };
+
+        match Self::parse(&text) {
+            Ok(config) => config,
+            Err(err) => {
+                diagnostics::error(format!("{:?} err={}", path.as_ref(), err));
+                Config::default()
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        toml::from_str(text).map_err(|err| err.message().to_string())
+    }
+
+    fn user_path() -> Option<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+                PathBuf::from(home).join(".config")
+            }
+        };
+
+        Some(config_dir.join("rfind").join("config.toml"))
+    }
+
+    pub fn project_path(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+
+    pub fn merge(&mut self, other: Config) {
+        self.flags.extend(other.flags);
+        self.ignore.extend(other.ignore);
+        self.types.extend(other.types);
+        self.profiles.extend(other.profiles);
+
+        if other.colors.index.is_some() {
+            self.colors.index = other.colors.index;
+        }
+        if other.colors.path.is_some() {
+            self.colors.path = other.colors.path;
+        }
+        if other.colors.matched.is_some() {
+            self.colors.matched = other.colors.matched;
+        }
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        let mut scheme = ColorScheme::default();
+
+        for (name, color) in [
+            (&self.colors.index, &mut scheme.index),
+            (&self.colors.path, &mut scheme.path),
+            (&self.colors.matched, &mut scheme.matched),
+        ] {
+            let name = match name {
+                Some(name) => name,
+                None => continue,
+            };
+
+            match Color::try_from(name.as_str()) {
+                Ok(c) => *color = c,
+                Err(_) => diagnostics::error(format!("unknown color {}", name)),
+            }
+        }
+
+        scheme
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use crossterm::style::Color;
+
+    use super::{Config, PROJECT_FILE};
+    use crate::temp_file::TempFile;
+
+    const USER: &str = r#"
+flags = ["-l=50"]
+ignore = ["*.log"]
+
+[colors]
+matched = "yellow"
+
+[types]
+proto = ["*.proto"]
+web = ["*.html", "*.css"]
+
+[profiles.rust]
+flags = ["--dirs"]
+ignore = ["target"]
+"#;
+
+    const PROJECT: &str = r#"
+flags = ["--no-preview"]
+ignore = ["node_modules"]
+
+[colors]
+index = "blue"
+
+[types]
+proto = ["*.proto", "*.protodevel"]
+"#;
+
+    #[test]
+    fn merge_user_and_project_config() {
+        let mut config = Config::parse(USER).unwrap();
+        config.merge(Config::parse(PROJECT).unwrap());
+
+        assert_eq!(config.flags, vec!["-l=50", "--no-preview"]);
+        assert_eq!(config.ignore, vec!["*.log", "node_modules"]);
+        assert_eq!(config.profiles["rust"].ignore, vec!["target"]);
+        assert_eq!(config.types["proto"], vec!["*.proto", "*.protodevel"]);
+        assert_eq!(config.types["web"], vec!["*.html", "*.css"]);
+
+        let scheme = config.color_scheme();
+        assert_eq!(scheme.index, Color::Blue);
+        assert_eq!(scheme.path, Color::Reset);
+        assert_eq!(scheme.matched, Color::Yellow);
+    }
+
+    #[test]
+    fn reject_unknown_keys() {
+        assert!(Config::parse("flag = [\"-l=5\"]").is_err());
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn find_project_file_in_parents() {
+        let tf = TempFile::new().unwrap();
+        let root = std::path::PathBuf::from(format!("{}.d", tf.name));
+        let nested = root.join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join(PROJECT_FILE), PROJECT).unwrap();
+
+        let found = Config::project_path(&nested);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(found, Some(root.join(PROJECT_FILE)));
+    }
+}
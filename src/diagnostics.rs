@@ -0,0 +1,83 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::error::Error;
+
+static NO_MESSAGES: AtomicBool = AtomicBool::new(false);
+static ERRORS: AtomicUsize = AtomicUsize::new(0);
+static PERMISSION_DENIED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_no_messages(no_messages: bool) {
+    NO_MESSAGES.store(no_messages, Ordering::Relaxed);
+}
+
+pub fn error<D: Display>(message: D) {
+    ERRORS.fetch_add(1, Ordering::Relaxed);
+
+    if !NO_MESSAGES.load(Ordering::Relaxed) {
+        eprintln!("[ERR] {}", message);
+    }
+}
+
+// For optional features that are turned off, not counted as an error.
+pub fn warn<D: Display>(message: D) {
+    if !NO_MESSAGES.load(Ordering::Relaxed) {
+        eprintln!("[WARN] {}", message);
+    }
+}
+
+pub fn report(err: &Error) {
+    match err {
+        Error::PermissionDenied { .. } => {
+            PERMISSION_DENIED.fetch_add(1, Ordering::Relaxed);
+        }
+        _ => error(err),
+    }
+}
+
+pub fn errors() -> usize {
+    ERRORS.load(Ordering::Relaxed) + PERMISSION_DENIED.load(Ordering::Relaxed)
+}
+
+pub fn permission_denied() -> usize {
+    PERMISSION_DENIED.load(Ordering::Relaxed)
+}
+
+pub fn summary() {
+    let denied = permission_denied();
+
+    if denied > 0 && !NO_MESSAGES.load(Ordering::Relaxed) {
+        eprintln!(
+            "[ERR] {} director{} skipped: permission denied",
+            denied,
+            if denied == 1 { "y" } else { "ies" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use std::io;
+
+    use super::{errors, permission_denied, report};
+    use crate::error::Error;
+
+    #[test]
+    fn count_errors_by_kind() {
+        let (all, denied) = (errors(), permission_denied());
+
+        report(&Error::read_dir(
+            "./secret",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        ));
+        report(&Error::io(
+            "./gone",
+            io::Error::from(io::ErrorKind::NotFound),
+        ));
+
+        assert!(errors() >= all + 2);
+        assert!(permission_denied() > denied);
+    }
+}
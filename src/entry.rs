@@ -0,0 +1,94 @@
+use std::{
+    cell::OnceCell,
+    fs::{self, FileType, Metadata},
+};
+
+use crate::path_style::PathStyle;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    path: String,
+    root: String,
+    depth: usize,
+    file_type: FileType,
+    metadata: OnceCell<Option<Metadata>>,
+}
+
+impl Entry {
+    pub fn new<S: AsRef<str>>(root: S, path: String, depth: usize, file_type: FileType) -> Entry {
+        Entry {
+            path,
+            root: root.as_ref().to_string(),
+            depth,
+            file_type,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn relative_path(&self) -> &str {
+        match self.path.strip_prefix(&self.root) {
+            Some(rest) => rest.trim_start_matches('/'),
+            None => &self.path,
+        }
+    }
+
+    pub fn absolute_path(&self) -> String {
+        PathStyle::Absolute.apply(&self.path)
+    }
+
+    pub fn file_name(&self) -> &str {
+        match self.path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => &self.path,
+        }
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type.is_dir()
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.file_type.is_file()
+    }
+
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| fs::symlink_metadata(&self.path).ok())
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod entry_tests {
+    use super::Entry;
+
+    #[test]
+    fn entry_paths() {
+        let file_type = std::fs::metadata("src/main.rs").unwrap().file_type();
+        let entry = Entry::new(".", "./src/main.rs".to_string(), 2, file_type);
+
+        assert_eq!(entry.relative_path(), "src/main.rs");
+        assert_eq!(entry.file_name(), "main.rs");
+        assert_eq!(entry.root(), ".");
+        assert_eq!(entry.depth(), 2);
+        assert!(entry.is_file());
+        assert!(entry.absolute_path().ends_with("/src/main.rs"));
+        assert!(entry.metadata().is_some_and(|m| m.len() > 0));
+    }
+}
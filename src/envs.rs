@@ -1,10 +1,49 @@
-use std::env;
+use std::{collections::HashMap, env, path::Path};
+
+use crate::{
+    config::{ColorScheme, Config},
+    diagnostics,
+    matcher::SearchMode,
+    path_style::PathStyle,
+    walker::Walker,
+};
 
 pub struct Envs {
     pub pattern: String,
+    pub patterns: Vec<String>,
+    pub and: bool,
+    pub fixed_strings: bool,
+    pub whole_segment: bool,
     pub max_output_lines: i32,
     pub interactive: bool,
     pub start_path: String,
+    pub roots: Vec<String>,
+    pub exec: Option<String>,
+    pub pick: bool,
+    pub print0: bool,
+    pub preview: bool,
+    pub dirs: bool,
+    pub shell_init: Option<String>,
+    pub profile: Option<String>,
+    pub no_config: bool,
+    pub ignore: Vec<String>,
+    pub ignore_files: Vec<String>,
+    pub excludes: Vec<String>,
+    pub hidden: bool,
+    pub no_ignore: bool,
+    pub no_ignore_vcs: bool,
+    pub extensions: Vec<String>,
+    pub types: Vec<String>,
+    pub types_not: Vec<String>,
+    pub type_defs: HashMap<String, Vec<String>>,
+    pub type_list: bool,
+    pub index_dir: Option<String>,
+    pub color: bool,
+    pub path_style: PathStyle,
+    pub quiet: bool,
+    pub max_results: Option<usize>,
+    pub no_messages: bool,
+    pub colors: ColorScheme,
 }
 
 impl Envs {
@@ -13,18 +52,163 @@ impl Envs {
             interactive: false,
             max_output_lines: 20,
             pattern: String::new(),
-            start_path: env::current_dir().unwrap().to_str().unwrap().to_string().replace(r"\", "/"),
+            patterns: Vec::new(),
+            and: false,
+            fixed_strings: false,
+            whole_segment: false,
+            start_path: ".".to_string(),
+            roots: Vec::new(),
+            exec: None,
+            pick: false,
+            print0: false,
+            preview: true,
+            dirs: false,
+            shell_init: None,
+            profile: None,
+            no_config: false,
+            ignore: Vec::new(),
+            ignore_files: Vec::new(),
+            excludes: Vec::new(),
+            hidden: false,
+            no_ignore: false,
+            no_ignore_vcs: false,
+            extensions: Vec::new(),
+            types: Vec::new(),
+            types_not: Vec::new(),
+            type_defs: HashMap::new(),
+            type_list: false,
+            index_dir: None,
+            color: true,
+            path_style: PathStyle::default(),
+            quiet: false,
+            max_results: None,
+            no_messages: false,
+            colors: ColorScheme::default(),
         };
 
-        for i in words.iter().skip(1) {
+        let mut words = words.iter().skip(1);
+        let mut unrestricted = 0;
+
+        while let Some(i) = words.next() {
             if i.starts_with("-l=") {
                 if let Some(stripped) = i.strip_prefix("-l=") {
                     result.max_output_lines = stripped.parse::<i32>().unwrap_or(20);
                 }
+            } else if i == "--shell-init" {
+                result.shell_init = words.next().cloned();
+            } else if let Some(stripped) = i.strip_prefix("--shell-init=") {
+                result.shell_init = Some(stripped.to_string());
+            } else if i == "--profile" {
+                result.profile = words.next().cloned();
+            } else if let Some(stripped) = i.strip_prefix("--profile=") {
+                result.profile = Some(stripped.to_string());
+            } else if i == "--no-config" {
+                result.no_config = true;
+            } else if i == "--ignore-file" {
+                result.ignore_files.extend(words.next().cloned());
+            } else if let Some(stripped) = i.strip_prefix("--ignore-file=") {
+                result.ignore_files.push(stripped.to_string());
+            } else if i == "-E" || i == "--exclude" {
+                result.excludes.extend(words.next().cloned());
+            } else if let Some(stripped) = i
+                .strip_prefix("-E=")
+                .or_else(|| i.strip_prefix("--exclude="))
+            {
+                result.excludes.push(stripped.to_string());
+            } else if i == "--hidden" || i == "-H" {
+                result.hidden = true;
+            } else if i == "--no-hidden" {
+                result.hidden = false;
+            } else if i == "--no-ignore" {
+                result.no_ignore = true;
+            } else if i == "--no-ignore-vcs" {
+                result.no_ignore_vcs = true;
+            } else if i.len() > 1 && i.starts_with('-') && i[1..].chars().all(|c| c == 'u') {
+                unrestricted += i.len() - 1;
+                result.no_ignore = true;
+                result.hidden |= unrestricted > 1;
+            } else if i == "--regexp" {
+                result.patterns.extend(words.next().cloned());
+            } else if let Some(stripped) = i.strip_prefix("--regexp=") {
+                result.patterns.push(stripped.to_string());
+            } else if i == "--and" {
+                result.and = true;
+            } else if i == "-F" || i == "--fixed-strings" {
+                result.fixed_strings = true;
+            } else if i == "--whole-segment" {
+                result.whole_segment = true;
+            } else if i == "-e" || i == "--extension" {
+                if let Some(extensions) = words.next() {
+                    result.push_extensions(extensions);
+                }
+            } else if let Some(stripped) = i
+                .strip_prefix("-e=")
+                .or_else(|| i.strip_prefix("--extension="))
+            {
+                result.push_extensions(stripped);
+            } else if i == "--type" {
+                result.types.extend(words.next().cloned());
+            } else if let Some(stripped) = i.strip_prefix("--type=") {
+                result.types.push(stripped.to_string());
+            } else if i == "--type-not" {
+                result.types_not.extend(words.next().cloned());
+            } else if let Some(stripped) = i.strip_prefix("--type-not=") {
+                result.types_not.push(stripped.to_string());
+            } else if i == "--type-list" {
+                result.type_list = true;
+            } else if i == "--index-dir" {
+                result.index_dir = words.next().cloned();
+            } else if let Some(stripped) = i.strip_prefix("--index-dir=") {
+                result.index_dir = Some(stripped.to_string());
+            } else if i == "--quiet" || i == "-q" {
+                result.quiet = true;
+            } else if i == "--first" {
+                result.max_results = Some(1);
+            } else if i == "--max-results" {
+                result.max_results = words
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|n| *n > 0);
+            } else if let Some(stripped) = i.strip_prefix("--max-results=") {
+                result.max_results = stripped.parse::<usize>().ok().filter(|n| *n > 0);
+            } else if i == "--no-messages" || i == "-s" {
+                result.no_messages = true;
+            } else if i == "--absolute-path" {
+                result.path_style = PathStyle::Absolute;
+            } else if i == "--canonicalize" {
+                result.path_style = PathStyle::Canonical;
+            } else if i == "--relative-to" {
+                if let Some(dir) = words.next() {
+                    result.path_style = PathStyle::RelativeTo(dir.clone());
+                }
+            } else if let Some(stripped) = i.strip_prefix("--relative-to=") {
+                result.path_style = PathStyle::RelativeTo(stripped.to_string());
+            } else if i == "--color" {
+                result.color = true;
+            } else if i == "--no-color" {
+                result.color = false;
+            } else if i == "--dirs" {
+                result.dirs = true;
+            } else if i == "--pick" {
+                result.pick = true;
+            } else if i == "--no-preview" {
+                result.preview = false;
+            } else if i == "--preview" {
+                result.preview = true;
+            } else if i == "--print0" || i == "-0" {
+                result.print0 = true;
+            } else if i == "--exec" {
+                result.exec = words.next().cloned();
+            } else if let Some(stripped) = i.strip_prefix("--exec=") {
+                result.exec = Some(stripped.to_string());
             } else if i.starts_with("-p") {
                 if let Some(stripped) = i.strip_prefix("-p=") {
-                    result.start_path = stripped.to_string();
+                    result.roots.push(stripped.to_string());
                 }
+            } else if !(result.pattern.is_empty() && result.patterns.is_empty())
+                && Path::new(i).is_dir()
+            {
+                result.roots.push(i.to_string());
             } else {
                 result.pattern.push_str(i.as_str());
                 result.pattern.push(' ');
@@ -32,17 +216,112 @@ impl Envs {
         }
         result.pattern = String::from(result.pattern.trim());
 
-        if result.pattern.is_empty() {
+        result.roots = Walker::dedup_roots(&result.roots);
+
+        match result.roots.first() {
+            Some(root) => result.start_path = root.clone(),
+            None => result.roots.push(result.start_path.clone()),
+        }
+
+        if result.pattern.is_empty() && result.patterns.is_empty() {
             result.interactive = true;
         }
 
         result
     }
+
+    pub fn search_patterns(&self) -> Vec<String> {
+        let mut patterns = self.patterns.clone();
+        if !self.pattern.is_empty() {
+            patterns.push(self.pattern.clone());
+        }
+        patterns
+    }
+
+    pub fn search_mode(&self) -> SearchMode {
+        SearchMode {
+            fixed_strings: self.fixed_strings,
+            whole_segment: self.whole_segment,
+            match_all: self.and,
+        }
+    }
+
+    fn push_extensions(&mut self, extensions: &str) {
+        self.extensions.extend(
+            extensions
+                .split(',')
+                .map(|ext| ext.trim().trim_start_matches('.'))
+                .filter(|ext| !ext.is_empty())
+                .map(String::from),
+        );
+    }
+
+    pub fn load(words: &[String]) -> Envs {
+        let vars = env::vars().collect::<HashMap<String, String>>();
+        let before_config = Self::layered(words, &vars, &[]);
+
+        // -s has to hold for broken config files and unknown profiles too.
+        diagnostics::set_no_messages(before_config.no_messages);
+
+        let config = if before_config.no_config {
+            Config::default()
+        } else {
+            Config::load()
+        };
+
+        Self::merge(words, &vars, &config)
+    }
+
+    pub fn merge(words: &[String], vars: &HashMap<String, String>, config: &Config) -> Envs {
+        let mut flags = config.flags.clone();
+        let mut ignore = config.ignore.clone();
+
+        if let Some(name) = &Self::layered(words, vars, &[]).profile {
+            match config.profiles.get(name) {
+                Some(profile) => {
+                    flags.extend(profile.flags.iter().cloned());
+                    ignore.extend(profile.ignore.iter().cloned());
+                }
+                None => diagnostics::error(format!("unknown profile {}", name)),
+            }
+        }
+
+        let mut result = Self::layered(words, vars, &flags);
+        result.ignore = ignore;
+        result.type_defs = config.types.clone();
+        result.colors = config.color_scheme();
+
+        result
+    }
+
+    fn layered(words: &[String], vars: &HashMap<String, String>, config_flags: &[String]) -> Envs {
+        let mut merged = words.iter().take(1).cloned().collect::<Vec<String>>();
+
+        if let Some(opts) = vars.get("RFIND_DEFAULT_OPTS") {
+            merged.extend(opts.split_whitespace().map(String::from));
+        }
+        if let Some(path) = vars.get("RFIND_IGNORE_FILE").filter(|v| !v.is_empty()) {
+            merged.push(format!("--ignore-file={}", path));
+        }
+        if let Some(dir) = vars.get("RFIND_INDEX_DIR").filter(|v| !v.is_empty()) {
+            merged.push(format!("--index-dir={}", dir));
+        }
+        if vars.get("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            merged.push("--no-color".to_string());
+        }
+
+        merged.extend(config_flags.iter().cloned());
+        merged.extend(words.iter().skip(1).cloned());
+
+        Envs::new(&merged)
+    }
 }
 
 #[cfg(test)]
 mod envs_tests {
-    use crate::envs::Envs;
+    use std::collections::HashMap;
+
+    use crate::{config::Config, envs::Envs, path_style::PathStyle};
 
     fn get_env_1() -> Vec<String> {
         vec![
@@ -64,4 +343,279 @@ mod envs_tests {
         assert!(!env.interactive);
         assert_eq!(env.max_output_lines, 11);
     }
+
+    #[test]
+    fn parsing_exec() {
+        let words = vec!["rfind".to_string(), "--exec=code -g {}".to_string()];
+
+        let env = Envs::new(&words);
+
+        assert_eq!(env.exec, Some("code -g {}".to_string()));
+        assert!(env.interactive);
+
+        let words = [
+            "rfind",
+            "--exec",
+            "code -g {}",
+            "--index-dir",
+            "/tmp/idx",
+            "main",
+        ]
+        .map(String::from);
+        let env = Envs::new(&words);
+
+        assert_eq!(env.exec, Some("code -g {}".to_string()));
+        assert_eq!(env.index_dir, Some("/tmp/idx".to_string()));
+        assert_eq!(env.pattern, "main");
+    }
+
+    #[test]
+    fn parsing_pick() {
+        let words = vec![
+            "rfind".to_string(),
+            "--pick".to_string(),
+            "-0".to_string(),
+            "main".to_string(),
+        ];
+
+        let env = Envs::new(&words);
+
+        assert!(env.pick);
+        assert!(env.print0);
+        assert_eq!(env.pattern, "main");
+    }
+
+    #[test]
+    fn parsing_shell_init() {
+        let words = vec![
+            "rfind".to_string(),
+            "--shell-init".to_string(),
+            "zsh".to_string(),
+        ];
+
+        let env = Envs::new(&words);
+
+        assert_eq!(env.shell_init, Some("zsh".to_string()));
+        assert!(env.pattern.is_empty());
+    }
+
+    #[test]
+    fn cli_overrides_config() {
+        let config = Config::parse(
+            r#"
+flags = ["-l=50", "--no-preview"]
+ignore = ["*.log"]
+
+[profiles.web]
+flags = ["-l=5"]
+ignore = ["node_modules"]
+"#,
+        )
+        .unwrap();
+
+        let words = vec!["rfind".to_string(), "main".to_string()];
+        let env = Envs::merge(&words, &HashMap::new(), &config);
+        assert_eq!(env.max_output_lines, 50);
+        assert!(!env.preview);
+        assert_eq!(env.ignore, vec!["*.log"]);
+        assert_eq!(env.pattern, "main");
+
+        let words = vec![
+            "rfind".to_string(),
+            "--profile=web".to_string(),
+            "--preview".to_string(),
+        ];
+        let env = Envs::merge(&words, &HashMap::new(), &config);
+        assert_eq!(env.max_output_lines, 5);
+        assert!(env.preview);
+        assert_eq!(env.ignore, vec!["*.log", "node_modules"]);
+
+        let words = vec![
+            "rfind".to_string(),
+            "--profile".to_string(),
+            "web".to_string(),
+            "-l=7".to_string(),
+        ];
+        let env = Envs::merge(&words, &HashMap::new(), &config);
+        assert_eq!(env.max_output_lines, 7);
+        assert!(env.interactive);
+    }
+
+    #[test]
+    fn env_vars_come_before_config_and_cli() {
+        let vars = HashMap::from([
+            (
+                "RFIND_DEFAULT_OPTS".to_string(),
+                "-l=30 --no-preview".to_string(),
+            ),
+            (
+                "RFIND_IGNORE_FILE".to_string(),
+                "/etc/rfind/ignore".to_string(),
+            ),
+            ("RFIND_INDEX_DIR".to_string(), "/var/tmp".to_string()),
+            ("NO_COLOR".to_string(), "1".to_string()),
+        ]);
+        let config = Config::parse("flags = [\"-l=40\"]").unwrap();
+
+        let words = vec!["rfind".to_string(), "--color".to_string()];
+        let env = Envs::merge(&words, &vars, &config);
+
+        assert_eq!(env.max_output_lines, 40);
+        assert!(!env.preview);
+        assert!(env.color);
+        assert_eq!(env.ignore_files, vec!["/etc/rfind/ignore"]);
+        assert_eq!(env.index_dir, Some("/var/tmp".to_string()));
+        assert!(env.interactive);
+
+        let vars = HashMap::from([("NO_COLOR".to_string(), String::new())]);
+        let words = vec!["rfind".to_string()];
+        assert!(Envs::merge(&words, &vars, &Config::default()).color);
+    }
+
+    #[test]
+    fn parsing_roots() {
+        let words = vec![
+            "rfind".to_string(),
+            "main".to_string(),
+            "src".to_string(),
+            "tests".to_string(),
+            "-p=docs".to_string(),
+        ];
+
+        let env = Envs::new(&words);
+
+        assert_eq!(env.pattern, "main");
+        assert_eq!(env.roots, vec!["src", "tests", "docs"]);
+        assert_eq!(env.start_path, "src");
+
+        let words = vec!["rfind".to_string(), "src".to_string()];
+        let env = Envs::new(&words);
+
+        assert_eq!(env.pattern, "src");
+        assert_eq!(env.roots, vec![env.start_path.clone()]);
+    }
+
+    #[test]
+    fn parsing_path_style() {
+        let words = vec!["rfind".to_string(), "--absolute-path".to_string()];
+        assert_eq!(Envs::new(&words).path_style, PathStyle::Absolute);
+
+        let words = vec![
+            "rfind".to_string(),
+            "--relative-to".to_string(),
+            "src".to_string(),
+            "main".to_string(),
+        ];
+        let env = Envs::new(&words);
+        assert_eq!(env.path_style, PathStyle::RelativeTo("src".to_string()));
+        assert_eq!(env.pattern, "main");
+    }
+
+    #[test]
+    fn parsing_max_results() {
+        let words = vec![
+            "rfind".to_string(),
+            "--max-results".to_string(),
+            "5".to_string(),
+            "main".to_string(),
+        ];
+        let env = Envs::new(&words);
+        assert_eq!(env.max_results, Some(5));
+        assert_eq!(env.pattern, "main");
+
+        let words = vec![
+            "rfind".to_string(),
+            "--first".to_string(),
+            "main".to_string(),
+        ];
+        assert_eq!(Envs::new(&words).max_results, Some(1));
+    }
+
+    #[test]
+    fn parsing_types() {
+        let words = [
+            "rfind",
+            "-e",
+            "cs,.csx",
+            "--extension=toml",
+            "--type",
+            "json",
+            "--type-not=test",
+            "main",
+        ]
+        .map(String::from);
+        let env = Envs::new(&words);
+
+        assert_eq!(env.extensions, vec!["cs", "csx", "toml"]);
+        assert_eq!(env.types, vec!["json"]);
+        assert_eq!(env.types_not, vec!["test"]);
+        assert_eq!(env.pattern, "main");
+    }
+
+    #[test]
+    fn parsing_ignore_toggles() {
+        let env = Envs::new(&["rfind", "-u", "main"].map(String::from));
+        assert!(env.no_ignore && !env.hidden);
+
+        let env = Envs::new(&["rfind", "-u", "-u", "main"].map(String::from));
+        assert!(env.no_ignore && env.hidden);
+
+        let env = Envs::new(&["rfind", "-uu", "--no-hidden", "main"].map(String::from));
+        assert!(env.no_ignore && !env.hidden);
+
+        let words = [
+            "rfind",
+            "--hidden",
+            "--no-ignore-vcs",
+            "--ignore-file",
+            "x",
+            "main",
+        ];
+        let env = Envs::new(&words.map(String::from));
+        assert!(env.hidden && env.no_ignore_vcs && !env.no_ignore);
+        assert_eq!(env.ignore_files, vec!["x"]);
+        assert_eq!(env.pattern, "main");
+
+        let words = [
+            "rfind",
+            "-E",
+            "target",
+            "--exclude=*.log",
+            "-E=dist/",
+            "main",
+        ];
+        let env = Envs::new(&words.map(String::from));
+        assert_eq!(env.excludes, vec!["target", "*.log", "dist/"]);
+        assert_eq!(env.pattern, "main");
+    }
+
+    #[test]
+    fn parsing_patterns() {
+        let words = [
+            "rfind",
+            "--regexp",
+            "main",
+            "--regexp=lib",
+            "--and",
+            "src",
+            "--regexp=rs$",
+        ];
+        let env = Envs::new(&words.map(String::from));
+
+        assert_eq!(env.patterns, vec!["main", "lib", "rs$"]);
+        assert_eq!(env.roots, vec!["src"]);
+        assert!(env.and && !env.interactive);
+        assert_eq!(env.search_patterns(), vec!["main", "lib", "rs$"]);
+
+        let words = ["rfind", "--regexp", "main", "-e", "rs", "api"];
+        let env = Envs::new(&words.map(String::from));
+        assert_eq!(env.search_patterns(), vec!["main", "api"]);
+        assert_eq!(env.extensions, vec!["rs"]);
+        assert!(!env.search_mode().fixed_strings);
+
+        let words = ["rfind", "-F", "--whole-segment", "[id].tsx"];
+        let env = Envs::new(&words.map(String::from));
+        assert_eq!(env.pattern, "[id].tsx");
+        assert!(env.fixed_strings && env.whole_segment && !env.interactive);
+    }
 }
@@ -0,0 +1,133 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidPattern { pattern: String, message: String },
+    Io { path: String, source: io::Error },
+    PermissionDenied { path: String },
+    IndexCorrupted { path: String, message: String },
+    UnknownType { name: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io<S: AsRef<str>>(path: S, source: io::Error) -> Error {
+        Error::Io {
+            path: path.as_ref().to_string(),
+            source,
+        }
+    }
+
+    // Only directories the walker can't open count as skipped, any other
+    // permission error is a real failure.
+    pub fn read_dir<S: AsRef<str>>(path: S, source: io::Error) -> Error {
+        if source.kind() == io::ErrorKind::PermissionDenied {
+            Error::PermissionDenied {
+                path: path.as_ref().to_string(),
+            }
+        } else {
+            Error::io(path, source)
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::InvalidPattern { .. } | Error::UnknownType { .. } => None,
+            Error::Io { path, .. }
+            | Error::PermissionDenied { path }
+            | Error::IndexCorrupted { path, .. } => Some(path),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidPattern { pattern, message } => {
+                write!(f, "invalid pattern {:?} err={}", pattern, message)
+            }
+            Error::Io { path, source } => write!(f, "{:?} err={}", path, source),
+            Error::PermissionDenied { path } => write!(f, "{:?} err=permission denied", path),
+            Error::IndexCorrupted { path, message } => {
+                write!(f, "index {:?} is corrupted err={}", path, message)
+            }
+            Error::UnknownType { name } => {
+                write!(f, "unknown file type {:?}, see --type-list", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        match (self, other) {
+            (
+                Error::InvalidPattern { pattern, message },
+                Error::InvalidPattern {
+                    pattern: other_pattern,
+                    message: other_message,
+                },
+            ) => pattern == other_pattern && message == other_message,
+            (
+                Error::Io { path, source },
+                Error::Io {
+                    path: other_path,
+                    source: other_source,
+                },
+            ) => path == other_path && source.kind() == other_source.kind(),
+            (Error::PermissionDenied { path }, Error::PermissionDenied { path: other_path }) => {
+                path == other_path
+            }
+            (
+                Error::IndexCorrupted { path, message },
+                Error::IndexCorrupted {
+                    path: other_path,
+                    message: other_message,
+                },
+            ) => path == other_path && message == other_message,
+            (Error::UnknownType { name }, Error::UnknownType { name: other_name }) => {
+                name == other_name
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use std::io;
+
+    use super::Error;
+
+    #[test]
+    fn classify_io_errors() {
+        let denied = Error::read_dir("./secret", io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(
+            denied,
+            Error::PermissionDenied {
+                path: "./secret".to_string()
+            }
+        );
+        assert_eq!(denied.path(), Some("./secret"));
+
+        let unwritable = Error::io(
+            "/tmp/index",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert!(matches!(unwritable, Error::Io { .. }));
+
+        let missing = Error::io("./gone", io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(missing, Error::Io { .. }));
+        assert!(std::error::Error::source(&missing).is_some());
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, HashMap};
+
+use regex::Regex;
+
+use crate::{
+    error::{Error, Result},
+    matcher::IgnoreFilter,
+};
+
+pub const BUILTIN: &[(&str, &[&str])] = &[
+    ("c", &["*.c", "*.h"]),
+    (
+        "config",
+        &[
+            "*.json",
+            "*.toml",
+            "*.yaml",
+            "*.yml",
+            "*.ini",
+            "*.cfg",
+            "*.conf",
+            "*.env",
+            "*.properties",
+        ],
+    ),
+    (
+        "cpp",
+        &["*.cpp", "*.cc", "*.cxx", "*.hpp", "*.hh", "*.hxx", "*.h"],
+    ),
+    ("csharp", &["*.cs", "*.csx"]),
+    ("css", &["*.css", "*.scss", "*.sass", "*.less"]),
+    ("go", &["*.go"]),
+    ("html", &["*.html", "*.htm"]),
+    ("java", &["*.java"]),
+    ("js", &["*.js", "*.mjs", "*.cjs", "*.jsx"]),
+    ("json", &["*.json", "*.jsonc"]),
+    ("kotlin", &["*.kt", "*.kts"]),
+    ("lua", &["*.lua"]),
+    ("markdown", &["*.md", "*.markdown"]),
+    ("php", &["*.php"]),
+    ("py", &["*.py", "*.pyi"]),
+    ("ruby", &["*.rb", "Gemfile", "Rakefile"]),
+    ("rust", &["*.rs"]),
+    ("sh", &["*.sh", "*.bash", "*.zsh", "*.fish"]),
+    ("sql", &["*.sql"]),
+    ("swift", &["*.swift"]),
+    (
+        "test",
+        &[
+            "*_test.*",
+            "*.test.*",
+            "*.spec.*",
+            "test_*.py",
+            "*Test.java",
+            "*Test.cs",
+            "*Tests.cs",
+        ],
+    ),
+    ("toml", &["*.toml"]),
+    ("ts", &["*.ts", "*.tsx", "*.mts", "*.cts"]),
+    ("xml", &["*.xml", "*.xsd", "*.xsl"]),
+    ("yaml", &["*.yaml", "*.yml"]),
+];
+
+pub struct FileTypes {
+    types: BTreeMap<String, Vec<String>>,
+}
+
+impl FileTypes {
+    pub fn new(user_types: &HashMap<String, Vec<String>>) -> FileTypes {
+        let mut types = BUILTIN
+            .iter()
+            .map(|(name, globs)| {
+                let globs = globs.iter().map(|glob| glob.to_string()).collect();
+                (name.to_string(), globs)
+            })
+            .collect::<BTreeMap<String, Vec<String>>>();
+
+        types.extend(user_types.clone());
+
+        FileTypes { types }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.types.get(name).map(Vec::as_slice)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.types.iter()
+    }
+
+    pub fn filter<S: AsRef<str>>(
+        &self,
+        types: &[S],
+        types_not: &[S],
+        extensions: &[S],
+    ) -> Result<TypeFilter> {
+        let mut select = extensions
+            .iter()
+            .map(|ext| format!("*.{}", ext.as_ref()))
+            .collect::<Vec<String>>();
+        select.extend(self.globs(types)?);
+
+        Ok(TypeFilter {
+            select: name_regex(&select),
+            reject: name_regex(&self.globs(types_not)?),
+        })
+    }
+
+    fn globs<S: AsRef<str>>(&self, names: &[S]) -> Result<Vec<String>> {
+        let mut globs = Vec::new();
+
+        for name in names {
+            match self.get(name.as_ref()) {
+                Some(found) => globs.extend(found.iter().cloned()),
+                None => {
+                    return Err(Error::UnknownType {
+                        name: name.as_ref().to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(globs)
+    }
+}
+
+// Keeps the files whose name matches one of the selected types or
+// extensions and drops the rejected ones, directories always pass.
+#[derive(Clone, Default)]
+pub struct TypeFilter {
+    select: Option<Regex>,
+    reject: Option<Regex>,
+}
+
+impl TypeFilter {
+    pub fn is_empty(&self) -> bool {
+        self.select.is_none() && self.reject.is_none()
+    }
+}
+
+impl IgnoreFilter for TypeFilter {
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        if is_dir {
+            return false;
+        }
+
+        let name = match path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => path,
+        };
+
+        self.select.as_ref().is_some_and(|r| !r.is_match(name))
+            || self.reject.as_ref().is_some_and(|r| r.is_match(name))
+    }
+}
+
+fn name_regex(globs: &[String]) -> Option<Regex> {
+    if globs.is_empty() {
+        return None;
+    }
+
+    let alternatives = globs
+        .iter()
+        .map(|glob| {
+            glob.split('*')
+                .map(|part| {
+                    part.split('?')
+                        .map(regex::escape)
+                        .collect::<Vec<String>>()
+                        .join("[^/]")
+                })
+                .collect::<Vec<String>>()
+                .join("[^/]*")
+        })
+        .collect::<Vec<String>>();
+
+    Regex::new(&format!("^(?:{})$", alternatives.join("|"))).ok()
+}
+
+#[cfg(test)]
+mod file_types_tests {
+    use std::collections::HashMap;
+
+    use super::FileTypes;
+    use crate::{error::Error, matcher::IgnoreFilter};
+
+    #[test]
+    fn select_types_and_extensions() {
+        let types = FileTypes::new(&HashMap::new());
+
+        let filter = types.filter(&["csharp"], &[], &["toml"]).unwrap();
+        assert!(!filter.is_ignored("./src/Program.cs", false));
+        assert!(!filter.is_ignored("./Cargo.toml", false));
+        assert!(filter.is_ignored("./src/main.rs", false));
+        assert!(filter.is_ignored("./src/cs", false));
+        assert!(!filter.is_ignored("./src", true));
+
+        let filter = types.filter(&["rust"], &["test"], &[]).unwrap();
+        assert!(!filter.is_ignored("./src/main.rs", false));
+        assert!(filter.is_ignored("./src/walker_test.rs", false));
+    }
+
+    #[test]
+    fn user_types_and_unknown_names() {
+        let user = HashMap::from([("proto".to_string(), vec!["*.proto".to_string()])]);
+        let types = FileTypes::new(&user);
+
+        let filter = types.filter(&["proto"], &[], &[]).unwrap();
+        assert!(!filter.is_ignored("./api/v1.proto", false));
+        assert!(filter.is_ignored("./api/v1.json", false));
+
+        assert!(matches!(
+            types.filter(&["nope"], &[], &[]),
+            Err(Error::UnknownType { name }) if name == "nope"
+        ));
+    }
+}
@@ -1,48 +1,217 @@
 use std::{
-    io::{self, BufWriter, Write},
+    cell::{Cell, RefCell},
+    io::{self, BufWriter, IsTerminal, Write},
     sync::{
         atomic::{AtomicBool, AtomicI32, Ordering},
         Arc, Mutex,
     },
+    thread,
+    time::Duration,
 };
 
 use crossterm::{
-    event::{read, Event, KeyCode, KeyEventKind},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    cursor::MoveToColumn,
+    event::{poll, read, Event, KeyCode, KeyEventKind},
+    queue,
+    terminal::{Clear, ClearType},
 };
 
-use crate::{envs::Envs, regex_helper::RegexHelper, temp_file, walker::Walker};
+use crate::{
+    action::Action,
+    diagnostics,
+    entry::Entry,
+    envs::Envs,
+    error::{Error, Result},
+    file_types::{FileTypes, TypeFilter},
+    history::History,
+    ignore_rules::{IgnoreRules, LoadedRules},
+    matcher::{IgnoreAny, IgnoreFilter, Matcher},
+    path_style,
+    picker::Picker,
+    prompt::Prompt,
+    temp_file,
+    terminal::{self, RawMode},
+    walker::{WalkControl, Walker},
+    watcher::{WatchEvent, Watcher},
+};
 
-use temp_file::{FindResult, TempFile};
+use temp_file::{FindResult, MatchCount, TempFile};
 
 pub struct FindMode {}
 
+const INTERACTIVE_HELP: &str =
+    "Esc or Ctrl-C cancels a search / Up, Down and Ctrl-R recall queries / \
+:N opens result N in $EDITOR, :y N copies it, :p N prints it and exits, :x N runs --exec";
+
+enum PageEnd {
+    Limit,
+    Eof,
+    Cancelled,
+}
+
+enum More {
+    Next,
+    All,
+}
+
+// Ignore files and the type filter are loaded once per run, every root
+// only adds its own .gitignore.
+#[derive(Clone, Default)]
+pub struct Ignores {
+    rules: LoadedRules,
+    types: TypeFilter,
+}
+
+impl Ignores {
+    pub fn load(program_envs: &Envs) -> Result<Ignores> {
+        Ok(Ignores {
+            types: FindMode::type_filter(program_envs)?,
+            rules: FindMode::ignore_rules(program_envs).load(),
+        })
+    }
+
+    pub fn root(&self, root: &str) -> Arc<dyn IgnoreFilter> {
+        let ignore = self.rules.build(root);
+
+        if self.types.is_empty() {
+            Arc::new(ignore)
+        } else {
+            Arc::new(IgnoreAny::default().with(ignore).with(self.types.clone()))
+        }
+    }
+}
+
 impl FindMode {
-    pub fn straight(program_envs: Envs) -> io::Result<()> {
-        let s = match RegexHelper::from_string(&program_envs.pattern) {
-            Ok(s) => s,
-            Err(err) => {
-                println!("[ERR] err={}", err);
-                return Ok(());
+    pub fn straight(program_envs: Envs) -> Result<bool> {
+        let s = program_envs
+            .search_mode()
+            .matcher(&program_envs.search_patterns())?;
+        let ignores = Ignores::load(&program_envs)?;
+
+        let colored = program_envs.color && io::stdout().is_terminal();
+        let stdout = RefCell::new(io::stdout().lock());
+        let found = Cell::new(0);
+        let stopped = Cell::new(false);
+        let max_results = match program_envs.quiet {
+            true => Some(1),
+            false => program_envs.max_results,
+        };
+
+        for root in &program_envs.roots {
+            let walked = Walker::walk(
+                root,
+                &|entry| {
+                    let node_name = entry.path();
+                    if !s.is_match(node_name) {
+                        return WalkControl::Continue;
+                    }
+
+                    found.set(found.get() + 1);
+                    if max_results.is_some_and(|max| found.get() >= max) {
+                        stopped.set(true);
+                    }
+
+                    if program_envs.quiet {
+                        return WalkControl::Stop;
+                    }
+
+                    let shown = program_envs.path_style.apply(node_name);
+                    let written = if colored {
+                        let ranges =
+                            path_style::shown_ranges(node_name, &shown, s.find_ranges(node_name));
+                        writeln!(
+                            stdout.borrow_mut(),
+                            "{}",
+                            terminal::highlight(&shown, &ranges, &program_envs.colors)
+                        )
+                    } else {
+                        writeln!(stdout.borrow_mut(), "{}", shown)
+                    };
+
+                    if let Err(err) = written {
+                        if err.kind() != io::ErrorKind::BrokenPipe {
+                            diagnostics::report(&Error::io("<stdout>", err));
+                        }
+                        stopped.set(true);
+                    }
+
+                    match stopped.get() {
+                        true => WalkControl::Stop,
+                        false => WalkControl::Continue,
+                    }
+                },
+                &*ignores.root(root),
+            );
+
+            if let Err(err) = walked {
+                diagnostics::report(&err);
+            }
+
+            if stopped.get() {
+                break;
             }
+        }
+
+        Ok(found.get() > 0)
+    }
+
+    fn ignore_rules(program_envs: &Envs) -> IgnoreRules {
+        let (mut globs, files) = match program_envs.no_ignore {
+            true => (Vec::new(), Vec::new()),
+            false => (
+                program_envs.ignore.clone(),
+                program_envs.ignore_files.clone(),
+            ),
         };
+        globs.extend(program_envs.excludes.iter().cloned());
+
+        IgnoreRules {
+            hidden: program_envs.hidden,
+            vcs: !program_envs.no_ignore && !program_envs.no_ignore_vcs,
+            globs,
+            files,
+        }
+    }
 
-        let ignore = RegexHelper::default();
+    fn type_filter(program_envs: &Envs) -> Result<TypeFilter> {
+        FileTypes::new(&program_envs.type_defs).filter(
+            &program_envs.types,
+            &program_envs.types_not,
+            &program_envs.extensions,
+        )
+    }
 
-        Walker::walk(
-            &program_envs.start_path,
-            &|node_name| {
-                if s.check(node_name) {
-                    println!("{}", node_name);
-                };
-            },
-            &ignore,
-        )?;
+    pub fn type_list(program_envs: Envs) -> Result<bool> {
+        let types = FileTypes::new(&program_envs.type_defs);
+        let mut stdout = io::stdout().lock();
+
+        for (name, globs) in types.list() {
+            if let Err(err) = writeln!(stdout, "{}: {}", name, globs.join(", ")) {
+                if err.kind() != io::ErrorKind::BrokenPipe {
+                    return Err(Error::io("<stdout>", err));
+                }
+                break;
+            }
+        }
 
-        Ok(())
+        Ok(true)
     }
 
-    pub fn interactive_init(tf: &TempFile, program_envs: &Envs) {
+    fn temp_file(program_envs: &Envs) -> Result<TempFile> {
+        match &program_envs.index_dir {
+            Some(dir) => TempFile::new_in(dir),
+            None => TempFile::new(),
+        }
+    }
+
+    // Watchers are the ones interactive_watch gave for the same roots, or
+    // none. The index walk adds their directory watches.
+    pub fn interactive_init(
+        tf: &TempFile,
+        program_envs: &Envs,
+        ignores: &Ignores,
+        watchers: &[Watcher],
+    ) {
         let to_write = match &tf.write {
             Some(write_f) => write_f,
             None => {
@@ -50,138 +219,331 @@ impl FindMode {
             }
         };
 
-        let ignore = RegexHelper::default();
-
         let arc_tf = Arc::new(Mutex::new(BufWriter::new(to_write)));
 
-        let _ = Walker::walk(
-            &program_envs.start_path,
-            &|node_name| {
-                let write_state = arc_tf
-                    .lock()
-                    .unwrap()
-                    .write_fmt(format_args!("{}\n", node_name));
+        let on_node = |entry: &Entry| {
+            let write_state = arc_tf
+                .lock()
+                .unwrap()
+                .write_fmt(format_args!("{}\n", entry.path()));
 
-                match write_state {
-                    Ok(_) => {}
-                    Err(err) => println!("[ERR] cant write err={}", err),
-                }
-            },
-            &ignore,
-        );
+            match write_state {
+                Ok(_) => {}
+                Err(err) => diagnostics::report(&Error::io(&tf.name, err)),
+            }
+        };
+
+        for (i, root) in program_envs.roots.iter().enumerate() {
+            let ignore = Self::index_ignore(ignores, root, watchers.get(i));
+
+            let walked = if program_envs.dirs {
+                Walker::walk_dirs(root, &on_node, &*ignore)
+            } else {
+                Walker::walk(root, &on_node, &*ignore)
+            };
+
+            if let Err(err) = walked {
+                diagnostics::report(&err);
+            }
+        }
 
         _ = arc_tf.lock().unwrap().flush();
     }
 
-    fn read_from_stdin() -> Option<String> {
-        print!("> ");
-        std::io::stdout().flush().unwrap();
-        let mut pattern = String::new();
+    fn index_ignore(
+        ignores: &Ignores,
+        root: &str,
+        watcher: Option<&Watcher>,
+    ) -> Arc<dyn IgnoreFilter> {
+        match watcher {
+            Some(watcher) => Arc::new(watcher.filter(ignores.root(root))),
+            None => ignores.root(root),
+        }
+    }
 
-        enable_raw_mode().unwrap();
+    pub fn interactive_watch(program_envs: &Envs, ignores: &Ignores) -> Vec<Watcher> {
+        if program_envs.dirs {
+            return Vec::new();
+        }
 
-        loop {
-            match read() {
-                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
-                    match key_event.code {
-                        KeyCode::Esc => {
-                            disable_raw_mode().unwrap();
-                            println!();
-                            return None;
-                        }
-                        KeyCode::Enter => {
-                            disable_raw_mode().unwrap();
-                            println!();
-                            return Some(pattern.trim().to_string());
-                        }
-                        KeyCode::Backspace => {
-                            if pattern.pop().is_some() {
-                                print!("\u{8} \u{8}");
-                                std::io::stdout().flush().unwrap();
-                            }
-                        }
-                        KeyCode::Char(c) => {
-                            pattern.push(c);
-                            print!("{}", c);
-                            std::io::stdout().flush().unwrap();
-                        }
-                        _ => {}
-                    }
+        let mut watchers = Vec::new();
+
+        for root in &program_envs.roots {
+            match Watcher::root_only(root, ignores.root(root)) {
+                Ok(w) => watchers.push(w),
+                Err(err) => {
+                    diagnostics::warn(format!("file watching is disabled err={}", err));
+                    return Vec::new();
                 }
-                Ok(_) => {}
-                Err(_) => {
-                    disable_raw_mode().unwrap();
-                    println!();
-                    return None;
+            }
+        }
+
+        watchers
+    }
+
+    pub fn interactive_apply_changes(tf: &mut TempFile, watchers: &[Watcher]) {
+        for event in watchers.iter().flat_map(|w| w.drain()) {
+            match event {
+                WatchEvent::Created(node_name) => {
+                    if let Err(err) = tf.append(&node_name) {
+                        diagnostics::report(&err);
+                    }
                 }
+                WatchEvent::Removed(node_name) => tf.remove(&node_name),
             }
         }
     }
 
-    pub fn interactive_find_pattern(tf: &mut TempFile, pattern: &String, program_envs: &Envs) {
+    pub fn interactive_find_pattern(
+        tf: &mut TempFile,
+        pattern: &String,
+        program_envs: &Envs,
+    ) -> Vec<String> {
+        let search = match program_envs.search_mode().matcher(&[pattern]) {
+            Ok(search) => search,
+            Err(err) => {
+                let _ = writeln!(terminal::tty(), "[ERR] {}", err);
+                return Vec::new();
+            }
+        };
+
         tf.refresh();
-        let search = AtomicBool::new(true);
-        let found = AtomicI32::new(0);
-        while search.load(Ordering::Relaxed) {
-            let find_result = tf.find(pattern, &|f| {
-                let prev = found.fetch_add(1, Ordering::Relaxed);
-                if program_envs.max_output_lines < 0
-                    || found.load(Ordering::Relaxed) <= program_envs.max_output_lines
-                {
-                    println!("{}) {}", prev + 1, f);
+        let total = MatchCount::spawn_with(tf, search.clone());
+        let results = RefCell::new(Vec::new());
+        let mut limit = program_envs.max_output_lines;
+
+        loop {
+            match Self::interactive_page(tf, &*search, limit, &results, program_envs) {
+                PageEnd::Limit => {}
+                PageEnd::Eof => break,
+                PageEnd::Cancelled => {
+                    let _ = writeln!(terminal::tty(), "... cancelled");
+                    break;
                 }
-            });
+            }
+
+            limit = match Self::interactive_more(&total, results.borrow().len()) {
+                Some(More::Next) => program_envs.max_output_lines,
+                Some(More::All) => -1,
+                None => break,
+            };
+        }
+
+        let _ = writeln!(terminal::tty());
+
+        results.take()
+    }
+
+    fn interactive_page(
+        tf: &mut TempFile,
+        matcher: &dyn Matcher,
+        limit: i32,
+        results: &RefCell<Vec<String>>,
+        program_envs: &Envs,
+    ) -> PageEnd {
+        let search = AtomicBool::new(true);
+        let cancelled = AtomicBool::new(false);
+        let on_page = AtomicI32::new(0);
+        let mut eof = false;
 
-            if found.load(Ordering::Relaxed) >= program_envs.max_output_lines {
-                search.store(false, Ordering::Relaxed);
+        let raw_mode = RawMode::enable();
+        let eol = if raw_mode.is_ok() { "\r\n" } else { "\n" };
+
+        thread::scope(|scope| {
+            if raw_mode.is_ok() {
+                scope.spawn(|| terminal::wait_for_cancel(&search, &cancelled));
             }
 
-            match find_result {
-                FindResult::Error(err) => {
-                    println!("[ERR] {}", err);
-                    search.store(false, Ordering::Relaxed);
+            while search.load(Ordering::Relaxed) {
+                let find_result = tf.find_with(
+                    matcher,
+                    &|f| {
+                        let shown = program_envs.path_style.apply(f);
+                        results.borrow_mut().push(shown.clone());
+                        let index = results.borrow().len();
+                        let _ = if program_envs.color {
+                            let colors = &program_envs.colors;
+                            let ranges =
+                                path_style::shown_ranges(f, &shown, matcher.find_ranges(f));
+                            write!(
+                                terminal::tty(),
+                                "{} {}{}",
+                                terminal::numbered(index, colors),
+                                terminal::highlight(&shown, &ranges, colors),
+                                eol
+                            )
+                        } else {
+                            write!(terminal::tty(), "{}) {}{}", index, shown, eol)
+                        };
+
+                        let prev = on_page.fetch_add(1, Ordering::Relaxed);
+                        if limit >= 0 && prev + 1 >= limit {
+                            search.store(false, Ordering::Relaxed);
+                        }
+                    },
+                    &search,
+                );
+
+                match find_result {
+                    FindResult::Error(err) => {
+                        let _ = write!(terminal::tty(), "[ERR] {}{}", err, eol);
+                        eof = true;
+                    }
+                    FindResult::Read => {}
+                    FindResult::Eof => eof = true,
                 }
-                FindResult::Read => {}
-                FindResult::Eof => {
+
+                if eof {
                     search.store(false, Ordering::Relaxed);
                 }
             }
-        }
+        });
 
-        if program_envs.max_output_lines > 0 && found.load(Ordering::Relaxed) >= program_envs.max_output_lines {
-            println!("... some more\n");
+        if cancelled.load(Ordering::Relaxed) {
+            PageEnd::Cancelled
+        } else if eof {
+            PageEnd::Eof
         } else {
-            println!();
+            PageEnd::Limit
         }
     }
 
-    pub fn interactive(program_envs: Envs) -> io::Result<()> {
-        let mut tf = match TempFile::new() {
-            Ok(f) => f,
-            Err(err) => {
-                println!("[ERR] {}", err);
-                return Ok(());
+    fn interactive_more(total: &MatchCount, shown: usize) -> Option<More> {
+        let raw_mode = RawMode::enable().ok()?;
+        let mut tty = terminal::tty();
+
+        let mut drawn = None;
+
+        let more = loop {
+            if drawn != Some(total.get()) {
+                drawn = Some(total.get());
+                let total = match total.get() {
+                    Some(total) => total.to_string(),
+                    None => "counting...".to_string(),
+                };
+
+                let _ = queue!(tty, MoveToColumn(0), Clear(ClearType::UntilNewLine));
+                let _ = write!(
+                    tty,
+                    "-- {} of {} matches / Space next page, a show all, any other key for a new query --",
+                    shown, total
+                );
+                let _ = tty.flush();
+            }
+
+            match poll(Duration::from_millis(100)) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(_) => break None,
+            }
+
+            match read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    break match key_event.code {
+                        KeyCode::Char(' ') | KeyCode::Char('n') => Some(More::Next),
+                        KeyCode::Char('a') => Some(More::All),
+                        _ => None,
+                    };
+                }
+                Ok(_) => {}
+                Err(_) => break None,
             }
         };
 
+        let _ = queue!(tty, MoveToColumn(0), Clear(ClearType::CurrentLine));
+        let _ = tty.flush();
+        drop(raw_mode);
+
+        more
+    }
+
+    pub fn interactive_loop(tf: &mut TempFile, program_envs: &Envs, watchers: &[Watcher]) {
+        let _ = writeln!(terminal::tty(), "{}", INTERACTIVE_HELP);
+
+        let project = History::project_root(&program_envs.start_path);
+        let mut prompt = Prompt::new(History::load(project));
+        let mut results = Vec::new();
+
+        while let Some(pattern) = prompt.read_line() {
+            if let Some((action, index)) = Action::parse(&pattern) {
+                let path = match results.get(index - 1) {
+                    Some(path) => path,
+                    None => {
+                        let _ = writeln!(terminal::tty(), "[ERR] there is no result {}\n", index);
+                        continue;
+                    }
+                };
+
+                if let Err(err) = action.run(path, &program_envs.exec) {
+                    let _ = writeln!(terminal::tty(), "[ERR] {}\n", err);
+                } else if action == Action::Print {
+                    return;
+                }
+                continue;
+            }
+
+            Self::interactive_apply_changes(tf, watchers);
+            results = Self::interactive_find_pattern(tf, &pattern, program_envs);
+        }
+    }
+
+    pub fn pick(program_envs: Envs) -> Result<bool> {
+        let ignores = Ignores::load(&program_envs)?;
+        let mut tf = Self::temp_file(&program_envs)?;
+
+        FindMode::interactive_init(&tf, &program_envs, &ignores, &[]);
+
+        let selection = match Picker::new(&mut tf, &program_envs.pattern)
+            .patterns(program_envs.patterns.clone())
+            .search_mode(program_envs.search_mode())
+            .preview(program_envs.preview)
+            .run()
+            .map_err(|err| Error::io(terminal::TTY_PATH, err))?
+        {
+            Some(selection) if !selection.is_empty() => selection,
+            _ => return Ok(false),
+        };
+
+        let separator = if program_envs.print0 { '\0' } else { '\n' };
+        let mut stdout = io::stdout().lock();
+        selection
+            .iter()
+            .map(|node_name| program_envs.path_style.apply(node_name))
+            .try_for_each(|node_name| write!(stdout, "{}{}", node_name, separator))
+            .and_then(|_| stdout.flush())
+            .map_err(|err| Error::io("<stdout>", err))?;
+
+        Ok(true)
+    }
+
+    pub fn interactive(program_envs: Envs) -> Result<bool> {
+        let ignores = Ignores::load(&program_envs)?;
+        let mut tf = Self::temp_file(&program_envs)?;
+
         let start = std::time::Instant::now();
-        FindMode::interactive_init(&tf, &program_envs);
-        println!(
+        let watchers = Self::interactive_watch(&program_envs, &ignores);
+        FindMode::interactive_init(&tf, &program_envs, &ignores, &watchers);
+
+        let _ = writeln!(
+            terminal::tty(),
             "temp file: {} / took {} ms / press Esc to exit",
             tf.name,
             start.elapsed().as_millis()
         );
 
-        while let Some(pattern) = Self::read_from_stdin() {
-            Self::interactive_find_pattern(&mut tf, &pattern, &program_envs);
-        }
+        Self::interactive_loop(&mut tf, &program_envs, &watchers);
 
-        Ok(())
+        Ok(true)
     }
 }
 
 impl FindMode {
-    pub async fn interactive_init_async(tf: &TempFile, program_envs: &Envs) {
+    pub async fn interactive_init_async(
+        tf: &TempFile,
+        program_envs: &Envs,
+        ignores: &Ignores,
+        watchers: &[Watcher],
+    ) {
         let to_write = match &tf.write {
             Some(write_f) => write_f,
             None => {
@@ -189,51 +551,55 @@ impl FindMode {
             }
         };
 
-        let ignore = RegexHelper::default();
-
-        let arc_tf = Arc::new(Mutex::new(BufWriter::new(to_write)));
+        let mut writer = BufWriter::new(to_write);
 
-        let _ = Walker::walk_async(
-            &program_envs.start_path,
-            &|node_name| {
-                let write_state = arc_tf
-                    .lock()
-                    .unwrap()
-                    .write_fmt(format_args!("{}\n", node_name));
+        for (i, root) in program_envs.roots.iter().enumerate() {
+            let ignore = Self::index_ignore(ignores, root, watchers.get(i));
+            let mut stream = Walker::stream(root, ignore, Walker::CONCURRENCY);
+            let mut paths = Vec::new();
 
-                match write_state {
+            while let Some(entry) = stream.recv().await {
+                match entry {
+                    Ok(entry) if entry.is_dir() == program_envs.dirs => {
+                        paths.push(entry.path().to_string())
+                    }
                     Ok(_) => {}
-                    Err(err) => println!("[ERR] cant write err={}", err),
+                    Err(err) => diagnostics::report(&err),
                 }
-            },
-            &ignore,
-        )
-        .await;
-    }
+            }
 
-    pub async fn interactive_async(program_envs: Envs) -> io::Result<()> {
-        let mut tf = match TempFile::new() {
-            Ok(f) => f,
-            Err(err) => {
-                println!("[ERR] {}", err);
-                return Ok(());
+            // Directories are read concurrently, sorting keeps the result
+            // numbers the same between runs.
+            paths.sort_unstable();
+
+            for path in &paths {
+                if let Err(err) = writeln!(writer, "{}", path) {
+                    diagnostics::report(&Error::io(&tf.name, err));
+                }
             }
-        };
+        }
+
+        _ = writer.flush();
+    }
+
+    pub async fn interactive_async(program_envs: Envs) -> Result<bool> {
+        let ignores = Ignores::load(&program_envs)?;
+        let mut tf = Self::temp_file(&program_envs)?;
 
         let start = std::time::Instant::now();
 
-        FindMode::interactive_init_async(&tf, &program_envs).await;
+        let watchers = Self::interactive_watch(&program_envs, &ignores);
+        FindMode::interactive_init_async(&tf, &program_envs, &ignores, &watchers).await;
 
-        println!(
+        let _ = writeln!(
+            terminal::tty(),
             "temp file: {} / took {} ms / press Esc to exit",
             tf.name,
             start.elapsed().as_millis()
         );
 
-        while let Some(pattern) = Self::read_from_stdin() {
-            Self::interactive_find_pattern(&mut tf, &pattern, &program_envs);
-        }
+        Self::interactive_loop(&mut tf, &program_envs, &watchers);
 
-        Ok(())
+        Ok(true)
     }
 }
@@ -0,0 +1,376 @@
+use std::{collections::VecDeque, fs};
+
+pub use crate::entry::Entry;
+use crate::{
+    error::{Error, Result},
+    ignore_rules::{IgnoreRules, RootIgnore},
+    matcher::{IgnoreFilter, Matcher},
+    path_style,
+    regex_helper::RegexHelper,
+    walker::Walker,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Any,
+    File,
+    Dir,
+}
+
+pub struct Finder {
+    roots: Vec<String>,
+    patterns: Vec<String>,
+    match_all: bool,
+    matchers: Vec<Box<dyn Matcher>>,
+    filters: Vec<Box<dyn IgnoreFilter>>,
+    kind: Kind,
+    ignore: Vec<String>,
+    gitignore: bool,
+    hidden: bool,
+    max_depth: Option<usize>,
+    limit: Option<usize>,
+}
+
+impl Finder {
+    pub fn new<S: AsRef<str>>(root: S) -> Finder {
+        Finder {
+            roots: vec![root.as_ref().to_string()],
+            patterns: Vec::new(),
+            match_all: false,
+            matchers: Vec::new(),
+            filters: Vec::new(),
+            kind: Kind::File,
+            ignore: Vec::new(),
+            gitignore: true,
+            hidden: false,
+            max_depth: None,
+            limit: None,
+        }
+    }
+
+    pub fn root<S: AsRef<str>>(mut self, root: S) -> Self {
+        self.roots.push(root.as_ref().to_string());
+        self
+    }
+
+    pub fn pattern<S: AsRef<str>>(mut self, pattern: S) -> Self {
+        self.patterns.push(pattern.as_ref().to_string());
+        self
+    }
+
+    pub fn match_all(mut self, all: bool) -> Self {
+        self.match_all = all;
+        self
+    }
+
+    pub fn matcher<M: Matcher + 'static>(mut self, matcher: M) -> Self {
+        self.matchers.push(Box::new(matcher));
+        self
+    }
+
+    pub fn filter<I: IgnoreFilter + 'static>(mut self, filter: I) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn ignore<S: AsRef<str>>(mut self, glob: S) -> Self {
+        self.ignore.push(glob.as_ref().to_string());
+        self
+    }
+
+    pub fn gitignore(mut self, on: bool) -> Self {
+        self.gitignore = on;
+        self
+    }
+
+    pub fn hidden(mut self, on: bool) -> Self {
+        self.hidden = on;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn build(self) -> Entries {
+        let mut errors = VecDeque::new();
+
+        let search = match RegexHelper::from_patterns(&self.patterns) {
+            Ok(search) => search.match_all(self.match_all),
+            Err(err) => {
+                errors.push_back(err);
+                RegexHelper::default()
+            }
+        };
+
+        let roots = if errors.is_empty() {
+            Walker::dedup_roots(&self.roots).into()
+        } else {
+            VecDeque::new()
+        };
+
+        Entries {
+            roots,
+            root: String::new(),
+            stack: Vec::new(),
+            errors,
+            search,
+            ignore: RootIgnore::default(),
+            found: 0,
+            finder: self,
+        }
+    }
+
+    fn root_ignore(&self, root: &str) -> RootIgnore {
+        IgnoreRules {
+            hidden: self.hidden,
+            vcs: self.gitignore,
+            globs: self.ignore.clone(),
+            files: Vec::new(),
+        }
+        .build(root)
+    }
+}
+
+struct Frame {
+    dir: fs::ReadDir,
+    path: String,
+    depth: usize,
+}
+
+pub struct Entries {
+    finder: Finder,
+    roots: VecDeque<String>,
+    root: String,
+    stack: Vec<Frame>,
+    errors: VecDeque<Error>,
+    search: RegexHelper,
+    ignore: RootIgnore,
+    found: usize,
+}
+
+impl Entries {
+    fn open(&mut self, path: String, depth: usize) {
+        match fs::read_dir(&path) {
+            Ok(dir) => self.stack.push(Frame { dir, path, depth }),
+            Err(err) => self.errors.push_back(Error::read_dir(&path, err)),
+        }
+    }
+
+    fn ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.ignore.is_ignored(path, is_dir)
+            || self
+                .finder
+                .filters
+                .iter()
+                .any(|f| f.is_ignored(path, is_dir))
+    }
+
+    fn wanted(&self, path: &str, is_dir: bool) -> bool {
+        let kind = match self.finder.kind {
+            Kind::Any => true,
+            Kind::File => !is_dir,
+            Kind::Dir => is_dir,
+        };
+
+        kind && (self.search.is_empty() || self.search.check(path))
+            && self.finder.matchers.iter().all(|m| m.is_match(path))
+    }
+}
+
+impl Iterator for Entries {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.finder.limit.is_some_and(|limit| self.found >= limit) {
+                return None;
+            }
+
+            if let Some(err) = self.errors.pop_front() {
+                return Some(Err(err));
+            }
+
+            let frame = match self.stack.last_mut() {
+                Some(frame) => frame,
+                None => {
+                    self.root = path_style::normalize(self.roots.pop_front()?);
+                    self.ignore = self.finder.root_ignore(&self.root);
+                    self.open(self.root.clone(), 0);
+                    continue;
+                }
+            };
+
+            let information = match frame.dir.next() {
+                Some(Ok(information)) => information,
+                Some(Err(err)) => return Some(Err(Error::read_dir(&frame.path, err))),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let (path, depth) = (frame.path.clone(), frame.depth + 1);
+
+            // Only the root frame can be too deep, max_depth(0) yields nothing.
+            if self.finder.max_depth.is_some_and(|max| depth > max) {
+                self.stack.pop();
+                continue;
+            }
+
+            let file_type = match information.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+
+            let file_name = match information.file_name().into_string() {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+
+            let full_path = path_style::join(&path, &file_name);
+
+            if !(file_type.is_file() || file_type.is_dir())
+                || self.ignored(&full_path, file_type.is_dir())
+            {
+                continue;
+            }
+
+            let wanted = self.wanted(&full_path, file_type.is_dir());
+
+            if file_type.is_dir() && self.finder.max_depth.is_none_or(|max| depth < max) {
+                self.open(full_path.clone(), depth);
+            }
+
+            if wanted {
+                self.found += 1;
+                return Some(Ok(Entry::new(&self.root, full_path, depth, file_type)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod finder_tests {
+    use super::{Finder, Kind};
+    use crate::error::Error;
+
+    #[test]
+    fn find_files_by_pattern() {
+        let found = Finder::new(".")
+            .pattern(r"main\.rs$")
+            .build()
+            .map(|entry| entry.unwrap().path().to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(found, vec!["./src/main.rs"]);
+    }
+
+    #[test]
+    fn limit_depth_and_kind() {
+        let found = Finder::new(".")
+            .kind(Kind::Dir)
+            .max_depth(1)
+            .build()
+            .map(|entry| entry.unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(found.iter().any(|e| e.path() == "./src" && e.depth() == 1));
+        assert!(found.iter().all(|e| e.depth() == 1 && e.is_dir()));
+        assert!(!found.iter().any(|e| e.path() == "./Cargo.toml"));
+
+        assert_eq!(Finder::new(".").max_depth(0).build().count(), 0);
+    }
+
+    #[test]
+    fn ignore_and_limit() {
+        let found = Finder::new("./src")
+            .ignore("*.rs")
+            .build()
+            .map(|entry| entry.unwrap().path().to_string())
+            .collect::<Vec<String>>();
+        assert!(found.iter().all(|path| !path.ends_with(".rs")));
+
+        assert_eq!(Finder::new(".").limit(3).build().count(), 3);
+    }
+
+    #[test]
+    fn report_errors_as_items() {
+        let mut entries = Finder::new(".").pattern("(").build();
+        assert!(matches!(
+            entries.next(),
+            Some(Err(Error::InvalidPattern { .. }))
+        ));
+        assert!(entries.next().is_none());
+
+        let mut entries = Finder::new("./no/such/dir").build();
+        assert!(matches!(entries.next(), Some(Err(Error::Io { .. }))));
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn or_and_patterns() {
+        let count = |finder: Finder| finder.build().collect::<Result<Vec<_>, _>>().unwrap().len();
+
+        let any = count(Finder::new("./src").pattern("main").pattern("lib"));
+        assert_eq!(any, 2);
+
+        let all = count(
+            Finder::new("./src")
+                .pattern("main")
+                .pattern("lib")
+                .match_all(true),
+        );
+        assert_eq!(all, 0);
+    }
+
+    #[test]
+    fn plug_own_predicates() {
+        let found = Finder::new(".")
+            .matcher(|path: &str| path.ends_with(".rs"))
+            .filter(|path: &str, is_dir: bool| is_dir && path.ends_with("/tests"))
+            .build()
+            .map(|entry| entry.unwrap().path().to_string())
+            .collect::<Vec<String>>();
+
+        assert!(found.contains(&"./src/main.rs".to_string()));
+        assert!(found.iter().all(|path| path.ends_with(".rs")));
+        assert!(!found.iter().any(|path| path.starts_with("./tests/")));
+    }
+
+    #[test]
+    fn globs_ignore_below_an_absolute_root() {
+        let base = std::env::temp_dir().join(format!("rfind_finder_{}", std::process::id()));
+        let root = base.join("target").join("proj");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target\n").unwrap();
+        std::fs::write(root.join("src").join("main.rs"), "").unwrap();
+        std::fs::write(root.join("target").join("main.rs"), "").unwrap();
+
+        let root = root.to_str().unwrap().to_string();
+        let found = |finder: Finder| {
+            finder
+                .build()
+                .map(|entry| entry.unwrap().path().to_string())
+                .collect::<Vec<String>>()
+        };
+        let by_gitignore = found(Finder::new(&root).pattern("main"));
+        let by_glob = found(Finder::new(&root).pattern("main").ignore("proj"));
+        _ = std::fs::remove_dir_all(&base);
+
+        assert_eq!(by_gitignore, vec![format!("{}/src/main.rs", root)]);
+        assert_eq!(by_glob, by_gitignore);
+    }
+}
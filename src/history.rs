@@ -0,0 +1,165 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{config::PROJECT_FILE, diagnostics};
+
+pub struct History {
+    project: String,
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn load<S: AsRef<str>>(project: S) -> History {
+        match Self::default_path() {
+            Some(path) => Self::from_file(path, project),
+            None => History {
+                project: project.as_ref().to_string(),
+                entries: Vec::new(),
+                path: None,
+            },
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>, S: AsRef<str>>(path: P, project: S) -> History {
+        let project = project.as_ref().to_string();
+
+        let entries = match fs::File::open(path.as_ref()) {
+            Ok(file) => io::BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|l| match l.split_once('\t') {
+                    Some((p, query)) if p == project => Some(query.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        History {
+            project,
+            entries,
+            path: Some(path.as_ref().to_path_buf()),
+        }
+    }
+
+    // The closest directory holding .rfind.toml or .git, so every
+    // subdirectory of a project shares one history.
+    pub fn project_root<S: AsRef<str>>(dir: S) -> String {
+        let dir = fs::canonicalize(dir.as_ref()).unwrap_or_else(|_| PathBuf::from(dir.as_ref()));
+
+        let root = dir
+            .ancestors()
+            .find(|d| d.join(PROJECT_FILE).is_file() || d.join(".git").exists())
+            .unwrap_or(&dir);
+
+        root.to_string_lossy().replace('\\', "/")
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+                PathBuf::from(home).join(".local").join("share")
+            }
+        };
+
+        Some(data_dir.join("rfind").join("history"))
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn push<S: AsRef<str>>(&mut self, query: S) {
+        let query = query.as_ref().trim();
+
+        if query.is_empty() || self.entries.last().is_some_and(|l| l == query) {
+            return;
+        }
+
+        self.entries.push(query.to_string());
+
+        if let Err(err) = self.save(query) {
+            diagnostics::error(format!("cant save history err={}", err));
+        }
+    }
+
+    fn save(&self, query: &str) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(format!("{}\t{}\n", self.project, query).as_bytes())
+    }
+
+    pub fn search<S: AsRef<str>>(&self, needle: S, before: usize) -> Option<usize> {
+        self.entries
+            .iter()
+            .take(before)
+            .rposition(|e| e.contains(needle.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use crate::temp_file::TempFile;
+
+    use super::History;
+
+    #[test]
+    fn history_is_kept_per_project() {
+        let file = TempFile::new().unwrap();
+
+        let mut first = History::from_file(&file.name, "/first");
+        first.push("main.rs");
+        first.push("main.rs");
+        first.push("  ");
+        first.push("Cargo");
+
+        let mut second = History::from_file(&file.name, "/second");
+        second.push("README");
+
+        assert_eq!(
+            History::from_file(&file.name, "/first").entries(),
+            ["main.rs", "Cargo"]
+        );
+        assert_eq!(
+            History::from_file(&file.name, "/second").entries(),
+            ["README"]
+        );
+    }
+
+    #[test]
+    fn subdirectories_share_the_project_root() {
+        let root = History::project_root(".");
+
+        assert_eq!(History::project_root("./src"), root);
+        assert_eq!(History::project_root("tests"), root);
+        assert!(!root.ends_with("/src"));
+    }
+
+    #[test]
+    fn search_goes_backwards() {
+        let file = TempFile::new().unwrap();
+        let mut history = History::from_file(&file.name, "/project");
+        history.push("src/main.rs");
+        history.push("tests");
+        history.push("src/lib.rs");
+
+        assert_eq!(history.search("src", 3), Some(2));
+        assert_eq!(history.search("src", 2), Some(0));
+        assert_eq!(history.search("src", 0), None);
+        assert_eq!(history.search("docs", 3), None);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{diagnostics, matcher::IgnoreFilter, path_style, regex_helper::RegexHelper};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgnoreRules {
+    pub hidden: bool,
+    pub vcs: bool,
+    pub globs: Vec<String>,
+    pub files: Vec<String>,
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        IgnoreRules {
+            hidden: false,
+            vcs: true,
+            globs: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+}
+
+impl IgnoreRules {
+    pub fn build<S: AsRef<str>>(&self, root: S) -> RootIgnore {
+        self.load().build(root)
+    }
+
+    // Reads the ignore files once, so several roots don't report the same
+    // broken file again.
+    pub fn load(&self) -> LoadedRules {
+        let mut rules = RegexHelper::from_globs(&self.globs);
+
+        for path in &self.files {
+            match RegexHelper::from_ignore_file(path) {
+                Ok(from_file) => rules.extend(from_file),
+                Err(err) => diagnostics::report(&err),
+            }
+        }
+
+        LoadedRules {
+            hidden: self.hidden,
+            vcs: self.vcs,
+            rules,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LoadedRules {
+    hidden: bool,
+    vcs: bool,
+    rules: RegexHelper,
+}
+
+impl LoadedRules {
+    pub fn build<S: AsRef<str>>(&self, root: S) -> RootIgnore {
+        let mut rules = match self.vcs {
+            true => RegexHelper::from_gitignore(&root),
+            false => RegexHelper::default(),
+        };
+        rules.extend(self.rules.clone());
+
+        RootIgnore {
+            root: path_style::normalize(root),
+            hidden: self.hidden,
+            vcs: self.vcs,
+            rules,
+        }
+    }
+}
+
+// Hidden names and .git are checked on the last path segment only, so an
+// explicit root like ./.config is still walked. Globs see the path below
+// the root, a root under /tmp/target is not ignored by "target".
+#[derive(Clone, Default)]
+pub struct RootIgnore {
+    root: String,
+    hidden: bool,
+    vcs: bool,
+    rules: RegexHelper,
+}
+
+impl IgnoreFilter for RootIgnore {
+    fn is_ignored(&self, path: &str, _is_dir: bool) -> bool {
+        let name = match path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => path,
+        };
+
+        (!self.hidden && name.starts_with('.'))
+            || (self.vcs && name == ".git")
+            || self.rules.check(self.relative(path))
+    }
+}
+
+impl RootIgnore {
+    fn relative<'a>(&self, path: &'a str) -> &'a str {
+        match path.strip_prefix(self.root.as_str()) {
+            Some(rest) if rest.starts_with('/') || self.root.ends_with('/') => rest,
+            Some("") => "",
+            _ => path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod ignore_rules_tests {
+    use super::IgnoreRules;
+    use crate::matcher::IgnoreFilter;
+
+    #[test]
+    fn hidden_and_vcs_toggles() {
+        let default = IgnoreRules::default().build(".");
+        assert!(default.is_ignored("./.env", false));
+        assert!(default.is_ignored("./target", true));
+        assert!(!default.is_ignored("./src/digit.rs", false));
+
+        let hidden = IgnoreRules {
+            hidden: true,
+            ..IgnoreRules::default()
+        }
+        .build(".");
+        assert!(!hidden.is_ignored("./.gitignore", false));
+        assert!(hidden.is_ignored("./.git", true));
+
+        let everything = IgnoreRules {
+            hidden: true,
+            vcs: false,
+            ..IgnoreRules::default()
+        }
+        .build(".");
+        assert!(!everything.is_ignored("./.git", true));
+        assert!(!everything.is_ignored("./target", true));
+    }
+
+    #[test]
+    fn loaded_rules_are_shared_by_roots() {
+        let loaded = IgnoreRules {
+            globs: vec!["*.log".to_string()],
+            ..IgnoreRules::default()
+        }
+        .load();
+
+        assert!(loaded.build(".").is_ignored("./debug.log", false));
+        assert!(loaded.build(".").is_ignored("./target", true));
+        assert!(loaded.build("./src").is_ignored("./src/debug.log", false));
+        assert!(!loaded.build("./src").is_ignored("./src/lib.rs", false));
+    }
+
+    #[test]
+    fn globs_match_below_the_root() {
+        let loaded = IgnoreRules {
+            globs: vec!["root".to_string(), "target".to_string()],
+            ..IgnoreRules::default()
+        }
+        .load();
+
+        let absolute = loaded.build("/tmp/target/root/");
+        assert!(!absolute.is_ignored("/tmp/target/root", true));
+        assert!(!absolute.is_ignored("/tmp/target/root/src/main.rs", false));
+        assert!(absolute.is_ignored("/tmp/target/root/target", true));
+        assert!(absolute.is_ignored("/tmp/target/root/a/root/b.rs", false));
+
+        let slash = loaded.build("/");
+        assert!(slash.is_ignored("/root", true));
+        assert!(!slash.is_ignored("/home", true));
+    }
+}
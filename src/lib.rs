@@ -1,5 +1,24 @@
+pub mod action;
+pub mod config;
+pub mod diagnostics;
+pub mod entry;
 pub mod envs;
+pub mod error;
+pub mod file_types;
 pub mod find_mode;
+pub mod finder;
+pub mod history;
+pub mod ignore_rules;
+pub mod line_editor;
+pub mod matcher;
+pub mod path_style;
+pub mod picker;
+pub mod preview;
+pub mod prompt;
+pub mod shell;
 pub mod temp_file;
+pub mod terminal;
 pub mod regex_helper;
-pub mod walker;
\ No newline at end of file
+pub mod walk_stream;
+pub mod walker;
+pub mod watcher;
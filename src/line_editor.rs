@@ -0,0 +1,244 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Default)]
+pub struct LineEditor {
+    line: String,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    pub fn set_line<S: AsRef<str>>(&mut self, line: S) {
+        self.line = line.as_ref().to_string();
+        self.cursor = self.line.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+    }
+
+    pub fn on_key(&mut self, key_event: &KeyEvent) -> bool {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+
+        match key_event.code {
+            KeyCode::Left if ctrl || alt => self.word_left(),
+            KeyCode::Right if ctrl || alt => self.word_right(),
+            KeyCode::Left => self.left(),
+            KeyCode::Right => self.right(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.line.len(),
+            KeyCode::Backspace if ctrl || alt => self.delete_word_before(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.line.len(),
+            KeyCode::Char('b') if ctrl => self.left(),
+            KeyCode::Char('f') if ctrl => self.right(),
+            KeyCode::Char('b') if alt => self.word_left(),
+            KeyCode::Char('f') if alt => self.word_right(),
+            KeyCode::Char('d') if ctrl => self.delete(),
+            KeyCode::Char('w') if ctrl => self.delete_word_before(),
+            KeyCode::Char('u') if ctrl => {
+                self.line.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.line.truncate(self.cursor),
+            KeyCode::Char(c) if !ctrl && !alt => self.insert(c.encode_utf8(&mut [0; 4])),
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn insert<S: AsRef<str>>(&mut self, text: S) {
+        let text = text
+            .as_ref()
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect::<String>();
+
+        self.line.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.line[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.line[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|g| self.cursor + g.len())
+            .unwrap_or(self.line.len())
+    }
+
+    fn left(&mut self) {
+        self.cursor = self.prev_boundary();
+    }
+
+    fn right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    fn backspace(&mut self) {
+        let start = self.prev_boundary();
+        self.line.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.line.replace_range(self.cursor..end, "");
+    }
+
+    fn word_start_before(&self) -> usize {
+        let before = &self.line[..self.cursor];
+        let trimmed = before.trim_end();
+
+        match trimmed.rfind(char::is_whitespace) {
+            Some(i) => i + trimmed[i..].chars().next().map(char::len_utf8).unwrap_or(1),
+            None => 0,
+        }
+    }
+
+    fn word_left(&mut self) {
+        self.cursor = self.word_start_before();
+    }
+
+    fn word_right(&mut self) {
+        let after = &self.line[self.cursor..];
+        let skipped = after.len() - after.trim_start().len();
+
+        self.cursor += skipped
+            + after[skipped..]
+                .find(char::is_whitespace)
+                .unwrap_or(after.len() - skipped);
+    }
+
+    fn delete_word_before(&mut self) {
+        let start = self.word_start_before();
+        self.line.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn view(&self, width: usize) -> (&str, usize) {
+        let mut start = 0;
+        let mut cursor_width = self.line[..self.cursor].width();
+
+        for g in self.line[..self.cursor].graphemes(true) {
+            if width == 0 || cursor_width < width {
+                break;
+            }
+            start += g.len();
+            cursor_width -= g.width();
+        }
+
+        let mut end = start;
+        let mut line_width = 0;
+        for g in self.line[start..].graphemes(true) {
+            if line_width + g.width() > width {
+                break;
+            }
+            end += g.len();
+            line_width += g.width();
+        }
+
+        (&self.line[start..end], cursor_width)
+    }
+}
+
+#[cfg(test)]
+mod line_editor_tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::LineEditor;
+
+    fn press(editor: &mut LineEditor, code: KeyCode, modifiers: KeyModifiers) {
+        assert!(editor.on_key(&KeyEvent::new(code, modifiers)));
+    }
+
+    fn type_str(editor: &mut LineEditor, s: &str) {
+        for c in s.chars() {
+            press(editor, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+    }
+
+    #[test]
+    fn cursor_movement_and_delete() {
+        let mut editor = LineEditor::default();
+        type_str(&mut editor, "mainrs");
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        type_str(&mut editor, ".");
+        assert_eq!(editor.line(), "main.rs");
+
+        press(&mut editor, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Delete, KeyModifiers::NONE);
+        type_str(&mut editor, "M");
+        press(&mut editor, KeyCode::End, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(editor.line(), "Main.r");
+    }
+
+    #[test]
+    fn word_and_line_deletion() {
+        let mut editor = LineEditor::default();
+        type_str(&mut editor, "src tests  docs");
+        press(&mut editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(editor.line(), "src tests  ");
+        press(&mut editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(editor.line(), "src ");
+
+        press(&mut editor, KeyCode::Left, KeyModifiers::CONTROL);
+        press(&mut editor, KeyCode::Right, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(editor.line(), "s");
+
+        type_str(&mut editor, "rc");
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(editor.line(), "c");
+    }
+
+    #[test]
+    fn paste_replaces_control_characters() {
+        let mut editor = LineEditor::default();
+        editor.insert("a\nb\tc");
+        assert_eq!(editor.line(), "a b c");
+    }
+
+    #[test]
+    fn wide_and_combining_characters() {
+        let mut editor = LineEditor::default();
+        type_str(&mut editor, "日本e\u{301}x");
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        assert_eq!(editor.view(80), ("日本e\u{301}x", 5));
+
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(editor.line(), "日e\u{301}x");
+        assert_eq!(editor.view(80), ("日e\u{301}x", 2));
+    }
+
+    #[test]
+    fn view_scrolls_to_cursor() {
+        let mut editor = LineEditor::default();
+        type_str(&mut editor, "0123456789");
+        assert_eq!(editor.view(5), ("6789", 4));
+
+        press(&mut editor, KeyCode::Home, KeyModifiers::NONE);
+        assert_eq!(editor.view(5), ("01234", 0));
+    }
+}
@@ -1,17 +1,56 @@
+use rfind::diagnostics;
 use rfind::envs::Envs;
+use rfind::error::Result;
 use rfind::find_mode::FindMode;
-use std::io;
+use rfind::shell;
+use std::process::ExitCode;
 
-fn main() -> io::Result<()> {
+fn run(program_envs: Envs) -> Result<bool> {
+    if let Some(shell_name) = &program_envs.shell_init {
+        match shell::init_script(shell_name) {
+            Some(script) => print!("{}", script),
+            None => diagnostics::error(format!(
+                "unsupported shell {}, expected bash, zsh or fish",
+                shell_name
+            )),
+        }
+        Ok(true)
+    } else if program_envs.type_list {
+        FindMode::type_list(program_envs)
+    } else if program_envs.pick {
+        FindMode::pick(program_envs)
+    } else if program_envs.interactive {
+        match tokio::runtime::Builder::new_current_thread().build() {
+            Ok(runtime) => runtime.block_on(FindMode::interactive_async(program_envs)),
+            Err(_) => FindMode::interactive(program_envs),
+        }
+    } else {
+        FindMode::straight(program_envs)
+    }
+}
+
+fn main() -> ExitCode {
     let words: Vec<String> = std::env::args().collect();
 
-    let program_envs = Envs::new(&words);
+    let program_envs = Envs::load(&words);
+    diagnostics::set_no_messages(program_envs.no_messages);
+    let quiet = program_envs.quiet;
 
-    if program_envs.interactive {
-        FindMode::interactive(program_envs)?;
+    let found = match run(program_envs) {
+        Ok(found) => found,
+        Err(err) => {
+            diagnostics::report(&err);
+            false
+        }
+    };
+
+    diagnostics::summary();
+
+    if diagnostics::errors() > 0 && !(quiet && found) {
+        ExitCode::from(2)
+    } else if found {
+        ExitCode::SUCCESS
     } else {
-        FindMode::straight(program_envs)?;
+        ExitCode::from(1)
     }
-
-    Ok(())
 }
@@ -0,0 +1,451 @@
+use std::{collections::HashSet, ops::Range, sync::Arc};
+
+use aho_corasick::AhoCorasick;
+
+use crate::{
+    error::{Error, Result},
+    regex_helper::RegexHelper,
+};
+
+pub trait Matcher: Send + Sync {
+    fn is_match(&self, path: &str) -> bool;
+
+    fn find_ranges(&self, _path: &str) -> Vec<Range<usize>> {
+        Vec::new()
+    }
+}
+
+pub trait IgnoreFilter: Send + Sync {
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool;
+
+    // Walkers load the root .gitignore in place of an empty filter.
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl Matcher for RegexHelper {
+    fn is_match(&self, path: &str) -> bool {
+        self.check(path)
+    }
+
+    fn find_ranges(&self, path: &str) -> Vec<Range<usize>> {
+        RegexHelper::find_ranges(self, path)
+    }
+}
+
+impl IgnoreFilter for RegexHelper {
+    fn is_ignored(&self, path: &str, _is_dir: bool) -> bool {
+        self.check(path)
+    }
+
+    fn is_empty(&self) -> bool {
+        RegexHelper::is_empty(self)
+    }
+}
+
+impl<F: Fn(&str) -> bool + Send + Sync> Matcher for F {
+    fn is_match(&self, path: &str) -> bool {
+        self(path)
+    }
+}
+
+impl<F: Fn(&str, bool) -> bool + Send + Sync> IgnoreFilter for F {
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self(path, is_dir)
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Arc<M> {
+    fn is_match(&self, path: &str) -> bool {
+        (**self).is_match(path)
+    }
+
+    fn find_ranges(&self, path: &str) -> Vec<Range<usize>> {
+        (**self).find_ranges(path)
+    }
+}
+
+impl<I: IgnoreFilter + ?Sized> IgnoreFilter for Arc<I> {
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        (**self).is_ignored(path, is_dir)
+    }
+
+    fn is_empty(&self) -> bool {
+        (**self).is_empty()
+    }
+}
+
+#[derive(Clone)]
+pub struct GlobMatcher {
+    globs: RegexHelper,
+}
+
+impl GlobMatcher {
+    pub fn new<S: AsRef<str>>(globs: &[S]) -> GlobMatcher {
+        GlobMatcher {
+            globs: RegexHelper::from_globs(globs),
+        }
+    }
+}
+
+impl Matcher for GlobMatcher {
+    fn is_match(&self, path: &str) -> bool {
+        self.globs.check(path)
+    }
+
+    fn find_ranges(&self, path: &str) -> Vec<Range<usize>> {
+        self.globs.find_ranges(path)
+    }
+}
+
+impl IgnoreFilter for GlobMatcher {
+    fn is_ignored(&self, path: &str, _is_dir: bool) -> bool {
+        self.globs.check(path)
+    }
+}
+
+// Turns the typed patterns into a matcher: regexes by default, literals
+// searched in one pass with -F.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SearchMode {
+    pub fixed_strings: bool,
+    pub whole_segment: bool,
+    pub match_all: bool,
+}
+
+impl SearchMode {
+    pub fn matcher<S: AsRef<str>>(&self, patterns: &[S]) -> Result<Arc<dyn Matcher>> {
+        if self.fixed_strings {
+            let literals = LiteralMatcher::from_literals(patterns)?
+                .whole_segment(self.whole_segment)
+                .match_all(self.match_all);
+            return Ok(Arc::new(literals));
+        }
+
+        let regexes = match self.whole_segment {
+            true => RegexHelper::from_segment_patterns(patterns)?,
+            false => RegexHelper::from_patterns(patterns)?,
+        };
+        Ok(Arc::new(regexes.match_all(self.match_all)))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LiteralMatcher {
+    searcher: Option<AhoCorasick>,
+    count: usize,
+    whole_segment: bool,
+    all: bool,
+}
+
+impl LiteralMatcher {
+    pub fn new<S: AsRef<str>>(needle: S) -> LiteralMatcher {
+        Self::from_literals(&[needle]).unwrap_or_default()
+    }
+
+    pub fn from_literals<S: AsRef<str>>(needles: &[S]) -> Result<LiteralMatcher> {
+        let searcher = AhoCorasick::new(needles.iter().map(AsRef::as_ref)).map_err(|err| {
+            Error::InvalidPattern {
+                pattern: needles
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+                message: err.to_string(),
+            }
+        })?;
+
+        Ok(LiteralMatcher {
+            searcher: Some(searcher),
+            count: needles.len(),
+            whole_segment: false,
+            all: false,
+        })
+    }
+
+    pub fn whole_segment(mut self, whole_segment: bool) -> Self {
+        self.whole_segment = whole_segment;
+        self
+    }
+
+    pub fn match_all(mut self, all: bool) -> Self {
+        self.all = all;
+        self
+    }
+
+    // Overlapping matches, so "src" inside "srcs/src" still finds the
+    // second, whole segment hit.
+    fn hits<'a>(&'a self, path: &'a str) -> impl Iterator<Item = aho_corasick::Match> + 'a {
+        self.searcher
+            .iter()
+            .flat_map(move |searcher| searcher.find_overlapping_iter(path))
+            .filter(move |m| !self.whole_segment || is_segment(path, m.range()))
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn is_match(&self, path: &str) -> bool {
+        if !self.all {
+            return self.hits(path).next().is_some();
+        }
+
+        let found = self
+            .hits(path)
+            .map(|m| m.pattern().as_usize())
+            .collect::<HashSet<usize>>();
+        self.count > 0 && found.len() == self.count
+    }
+
+    fn find_ranges(&self, path: &str) -> Vec<Range<usize>> {
+        let mut ranges = self
+            .hits(path)
+            .map(|m| m.range())
+            .filter(|range| !range.is_empty())
+            .collect::<Vec<_>>();
+
+        ranges.sort_by_key(|range| range.start);
+        ranges
+    }
+}
+
+fn is_segment(path: &str, range: Range<usize>) -> bool {
+    (range.start == 0 || path[..range.start].ends_with('/'))
+        && (range.end == path.len() || path[range.end..].starts_with('/'))
+}
+
+// Every character of the pattern has to appear in order, lowercase
+// patterns ignore case like in most fuzzy finders.
+#[derive(Clone)]
+pub struct FuzzyMatcher {
+    chars: Vec<char>,
+    ignore_case: bool,
+}
+
+impl FuzzyMatcher {
+    pub fn new<S: AsRef<str>>(pattern: S) -> FuzzyMatcher {
+        let pattern = pattern.as_ref();
+
+        FuzzyMatcher {
+            chars: pattern.chars().collect(),
+            ignore_case: !pattern.chars().any(char::is_uppercase),
+        }
+    }
+
+    fn same(&self, a: char, b: char) -> bool {
+        if self.ignore_case {
+            a.to_lowercase().eq(b.to_lowercase())
+        } else {
+            a == b
+        }
+    }
+}
+
+impl Matcher for FuzzyMatcher {
+    fn is_match(&self, path: &str) -> bool {
+        self.find_ranges(path).len() == self.chars.len()
+    }
+
+    fn find_ranges(&self, path: &str) -> Vec<Range<usize>> {
+        let mut chars = self.chars.iter().peekable();
+        let mut ranges = Vec::new();
+
+        for (start, c) in path.char_indices() {
+            match chars.peek() {
+                Some(&&wanted) if self.same(c, wanted) => {
+                    chars.next();
+                    ranges.push(start..start + c.len_utf8());
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        if chars.peek().is_some() {
+            return Vec::new();
+        }
+
+        ranges
+    }
+}
+
+#[derive(Default)]
+pub struct AnyOf {
+    matchers: Vec<Box<dyn Matcher>>,
+}
+
+impl AnyOf {
+    pub fn with<M: Matcher + 'static>(mut self, matcher: M) -> Self {
+        self.matchers.push(Box::new(matcher));
+        self
+    }
+}
+
+impl Matcher for AnyOf {
+    fn is_match(&self, path: &str) -> bool {
+        self.matchers.iter().any(|m| m.is_match(path))
+    }
+
+    fn find_ranges(&self, path: &str) -> Vec<Range<usize>> {
+        ranges_of(&self.matchers, path)
+    }
+}
+
+#[derive(Default)]
+pub struct AllOf {
+    matchers: Vec<Box<dyn Matcher>>,
+}
+
+impl AllOf {
+    pub fn with<M: Matcher + 'static>(mut self, matcher: M) -> Self {
+        self.matchers.push(Box::new(matcher));
+        self
+    }
+}
+
+impl Matcher for AllOf {
+    fn is_match(&self, path: &str) -> bool {
+        self.matchers.iter().all(|m| m.is_match(path))
+    }
+
+    fn find_ranges(&self, path: &str) -> Vec<Range<usize>> {
+        ranges_of(&self.matchers, path)
+    }
+}
+
+#[derive(Default)]
+pub struct IgnoreAny {
+    filters: Vec<Box<dyn IgnoreFilter>>,
+}
+
+impl IgnoreAny {
+    pub fn with<I: IgnoreFilter + 'static>(mut self, filter: I) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+}
+
+impl IgnoreFilter for IgnoreAny {
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.filters.iter().any(|f| f.is_ignored(path, is_dir))
+    }
+}
+
+fn ranges_of(matchers: &[Box<dyn Matcher>], path: &str) -> Vec<Range<usize>> {
+    let mut ranges = matchers
+        .iter()
+        .flat_map(|m| m.find_ranges(path))
+        .collect::<Vec<_>>();
+
+    ranges.sort_by_key(|range| range.start);
+    ranges
+}
+
+#[cfg(test)]
+mod matcher_tests {
+    use super::{
+        AllOf, AnyOf, FuzzyMatcher, GlobMatcher, IgnoreAny, IgnoreFilter, LiteralMatcher, Matcher,
+        SearchMode,
+    };
+    use crate::regex_helper::RegexHelper;
+
+    #[test]
+    fn literal_and_glob() {
+        let literal = LiteralMatcher::new("a.");
+        assert!(literal.is_match("./a.rs"));
+        assert!(!literal.is_match("./abc"));
+        assert_eq!(literal.find_ranges("./a.b/a.c"), vec![2..4, 6..8]);
+
+        let glob = GlobMatcher::new(&["*.log"]);
+        assert!(glob.is_match("./app/debug.log"));
+        assert!(glob.is_ignored("./app/debug.log", false));
+        assert!(!glob.is_match("./src/main.rs"));
+    }
+
+    #[test]
+    fn several_literals_and_segments() {
+        let literals =
+            LiteralMatcher::from_literals(&["[id].tsx", "appsettings.Development.json"]).unwrap();
+        assert!(literals.is_match("./pages/[id].tsx"));
+        assert!(literals.is_match("./api/appsettings.Development.json"));
+        assert!(!literals.is_match("./api/appsettings.json"));
+        assert_eq!(literals.find_ranges("./p/[id].tsx"), vec![4..12]);
+
+        let segment = LiteralMatcher::from_literals(&["Attributes"])
+            .unwrap()
+            .whole_segment(true);
+        assert!(segment.is_match("./Api/Attributes/Some.cs"));
+        assert!(segment.is_match("./Api/Attributes"));
+        assert!(!segment.is_match("./Api/UseAttributesHelper.cs"));
+        assert_eq!(
+            segment.find_ranges("./AttributesX/Attributes"),
+            vec![14..24]
+        );
+
+        let all = LiteralMatcher::from_literals(&["src", ".rs"])
+            .unwrap()
+            .match_all(true);
+        assert!(all.is_match("./src/lib.rs"));
+        assert!(!all.is_match("./src/lib.c"));
+    }
+
+    #[test]
+    fn search_modes() {
+        let regex = SearchMode::default().matcher(&["a.c"]).unwrap();
+        assert!(regex.is_match("./abc"));
+
+        let fixed = SearchMode {
+            fixed_strings: true,
+            ..SearchMode::default()
+        };
+        assert!(!fixed.matcher(&["a.c"]).unwrap().is_match("./abc"));
+        assert!(fixed.matcher(&["("]).unwrap().is_match("./a(1)"));
+
+        let segment = SearchMode {
+            whole_segment: true,
+            match_all: true,
+            ..SearchMode::default()
+        }
+        .matcher(&["src", r"\w+\.rs"])
+        .unwrap();
+        assert!(segment.is_match("./src/lib.rs"));
+        assert!(!segment.is_match("./srcs/lib.rs"));
+        assert!(SearchMode::default().matcher(&["("]).is_err());
+    }
+
+    #[test]
+    fn fuzzy_matches_in_order() {
+        let fuzzy = FuzzyMatcher::new("smr");
+        assert!(fuzzy.is_match("./src/main.rs"));
+        assert!(fuzzy.is_match("./SRC/MAIN.RS"));
+        assert!(!fuzzy.is_match("./rms"));
+        assert_eq!(fuzzy.find_ranges("./sMr"), vec![2..3, 3..4, 4..5]);
+
+        assert!(!FuzzyMatcher::new("Smr").is_match("./src/main.rs"));
+    }
+
+    #[test]
+    fn composite_and_closures() {
+        let owners = |path: &str| path.starts_with("./src/");
+        let any = AnyOf::default()
+            .with(LiteralMatcher::new("README"))
+            .with(owners);
+        assert!(any.is_match("./README.md"));
+        assert!(any.is_match("./src/lib.rs"));
+        assert!(!any.is_match("./tests/it.rs"));
+
+        let all = AllOf::default()
+            .with(RegexHelper::from_string(r"\.rs$").unwrap())
+            .with(owners);
+        assert!(all.is_match("./src/lib.rs"));
+        assert!(!all.is_match("./tests/it.rs"));
+
+        let ignore = IgnoreAny::default()
+            .with(GlobMatcher::new(&["target"]))
+            .with(|_: &str, is_dir: bool| is_dir);
+        assert!(ignore.is_ignored("./target", false));
+        assert!(ignore.is_ignored("./src", true));
+        assert!(!ignore.is_ignored("./src/lib.rs", false));
+        assert!(!ignore.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum PathStyle {
+    #[default]
+    AsWalked,
+    Absolute,
+    RelativeTo(String),
+    Canonical,
+}
+
+impl PathStyle {
+    pub fn apply<S: AsRef<str>>(&self, node_name: S) -> String {
+        let node_name = node_name.as_ref();
+
+        match self {
+            PathStyle::AsWalked => node_name.to_string(),
+            PathStyle::Absolute => to_string(&absolute(node_name)),
+            PathStyle::RelativeTo(dir) => {
+                to_string(&relative(&absolute(node_name), &absolute(dir)))
+            }
+            PathStyle::Canonical => match std::fs::canonicalize(node_name) {
+                Ok(path) => to_string(&path),
+                Err(_) => to_string(&absolute(node_name)),
+            },
+        }
+    }
+}
+
+// Matches are found on the walked path, this moves their ranges onto the
+// printed one. Only the part both end with is kept, so a root rewritten
+// by the style is never highlighted.
+pub fn shown_ranges(walked: &str, shown: &str, ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let common = walked
+        .bytes()
+        .rev()
+        .zip(shown.bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let shift = |at: usize| at + shown.len() - walked.len();
+
+    ranges
+        .into_iter()
+        .filter(|range| range.start >= walked.len() - common)
+        .map(|range| shift(range.start)..shift(range.end))
+        .collect()
+}
+
+pub fn normalize<S: AsRef<str>>(path: S) -> String {
+    let path = path.as_ref();
+    let rooted = path.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+
+    for (i, part) in path.split('/').enumerate() {
+        match part {
+            "" => {}
+            "." if i > 0 || path == "." => {}
+            "." => parts.push(part),
+            ".." => match parts.last() {
+                Some(&"..") | None => {
+                    if !rooted {
+                        parts.push(part);
+                    }
+                }
+                Some(&".") => {
+                    parts.pop();
+                    parts.push(part);
+                }
+                Some(_) => {
+                    parts.pop();
+                }
+            },
+            _ => parts.push(part),
+        }
+    }
+
+    match (rooted, parts.is_empty()) {
+        (true, _) => format!("/{}", parts.join("/")),
+        (false, true) => ".".to_string(),
+        (false, false) => parts.join("/"),
+    }
+}
+
+pub fn join<S: AsRef<str>>(dir: S, name: &str) -> String {
+    let dir = dir.as_ref();
+
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn absolute(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    PathBuf::from(normalize(to_string(&joined)))
+}
+
+fn relative(path: &Path, base: &Path) -> PathBuf {
+    let path = path.components().collect::<Vec<Component>>();
+    let base = base.components().collect::<Vec<Component>>();
+
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    let mut result = PathBuf::new();
+    for _ in common..base.len() {
+        result.push("..");
+    }
+    for component in &path[common..] {
+        result.push(component);
+    }
+
+    if result.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        result
+    }
+}
+
+fn to_string(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod path_style_tests {
+    use super::{join, normalize, shown_ranges, PathStyle};
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!(normalize("./src//main.rs"), "./src/main.rs");
+        assert_eq!(normalize("src/../tests/./a.rs"), "tests/a.rs");
+        assert_eq!(normalize("./../x"), "../x");
+        assert_eq!(normalize("../../x/.."), "../..");
+        assert_eq!(normalize("/a/../../b/"), "/b");
+        assert_eq!(normalize("a/.."), ".");
+        assert_eq!(normalize("."), ".");
+        assert_eq!(normalize("//"), "/");
+    }
+
+    #[test]
+    fn join_paths() {
+        assert_eq!(join("/", "etc"), "/etc");
+        assert_eq!(join(".", "src"), "./src");
+    }
+
+    #[test]
+    fn move_ranges_to_the_shown_path() {
+        assert_eq!(
+            shown_ranges(
+                "./src/main.rs",
+                "/home/me/src/main.rs",
+                vec![0..1, 2..5, 6..10]
+            ),
+            vec![9..12, 13..17]
+        );
+        assert_eq!(
+            shown_ranges("./src/main.rs", "main.rs", vec![2..5, 6..10]),
+            vec![0..4]
+        );
+    }
+
+    #[test]
+    fn apply_styles() {
+        let cwd = std::env::current_dir().unwrap();
+        let cwd = cwd.to_str().unwrap();
+
+        assert_eq!(PathStyle::AsWalked.apply("./src"), "./src");
+        assert_eq!(
+            PathStyle::Absolute.apply("./src//main.rs"),
+            format!("{}/src/main.rs", cwd)
+        );
+        assert_eq!(
+            PathStyle::RelativeTo("src".to_string()).apply(format!("{}/tests/a.rs", cwd)),
+            "../tests/a.rs"
+        );
+        assert_eq!(
+            PathStyle::RelativeTo(".".to_string()).apply("./src/main.rs"),
+            "src/main.rs"
+        );
+        assert_eq!(
+            PathStyle::Canonical.apply("src/../src/main.rs"),
+            format!(
+                "{}/src/main.rs",
+                std::fs::canonicalize(cwd).unwrap().to_str().unwrap()
+            )
+        );
+    }
+}
@@ -0,0 +1,516 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    event::{
+        poll, read, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent,
+        KeyEventKind, KeyModifiers,
+    },
+    queue,
+    style::{Attribute, SetAttribute},
+    terminal::{
+        self as crossterm_terminal, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+    },
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    error::Error,
+    line_editor::LineEditor,
+    matcher::{Matcher, SearchMode},
+    preview::Preview,
+    temp_file::{FindResult, MatchCount, TempFile},
+    terminal::{self, RawMode},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum PickStep {
+    Continue,
+    Accept,
+    Abort,
+}
+
+pub struct Picker<'a> {
+    tf: &'a mut TempFile,
+    editor: LineEditor,
+    query: Option<String>,
+    patterns: Vec<String>,
+    search_mode: SearchMode,
+    search: Option<Arc<dyn Matcher>>,
+    matches: Vec<String>,
+    exhausted: bool,
+    error: Option<Error>,
+    total: Option<MatchCount>,
+    highlighted: usize,
+    offset: usize,
+    marked: Vec<String>,
+    rows: usize,
+    show_preview: bool,
+    preview: Option<(String, Vec<String>)>,
+}
+
+impl<'a> Picker<'a> {
+    pub fn new<S: AsRef<str>>(tf: &'a mut TempFile, query: S) -> Picker<'a> {
+        let mut editor = LineEditor::default();
+        editor.set_line(query);
+
+        Picker {
+            tf,
+            editor,
+            query: None,
+            patterns: Vec::new(),
+            search_mode: SearchMode::default(),
+            search: None,
+            matches: Vec::new(),
+            exhausted: false,
+            error: None,
+            total: None,
+            highlighted: 0,
+            offset: 0,
+            marked: Vec::new(),
+            rows: 20,
+            show_preview: false,
+            preview: None,
+        }
+    }
+
+    // Fixed patterns searched together with the typed query, like the
+    // --regexp patterns next to the free words on the command line.
+    pub fn patterns(mut self, patterns: Vec<String>) -> Picker<'a> {
+        self.patterns = patterns;
+        self
+    }
+
+    pub fn search_mode(mut self, search_mode: SearchMode) -> Picker<'a> {
+        self.search_mode = search_mode;
+        self
+    }
+
+    pub fn preview(mut self, show_preview: bool) -> Picker<'a> {
+        self.show_preview = show_preview;
+        self
+    }
+
+    pub fn run(&mut self) -> io::Result<Option<Vec<String>>> {
+        let raw_mode = RawMode::enable()?;
+        queue!(terminal::tty(), EnterAlternateScreen, EnableBracketedPaste)?;
+
+        let step = self.event_loop();
+
+        let _ = queue!(terminal::tty(), DisableBracketedPaste, LeaveAlternateScreen);
+        let _ = terminal::tty().flush();
+        drop(raw_mode);
+
+        match step? {
+            PickStep::Accept => Ok(Some(self.selection())),
+            _ => Ok(None),
+        }
+    }
+
+    fn event_loop(&mut self) -> io::Result<PickStep> {
+        loop {
+            let (columns, rows) = match crossterm_terminal::size() {
+                Ok((c, r)) if c > 0 && r > 2 => (c as usize, r as usize),
+                _ => (80, 24),
+            };
+            self.rows = rows - 2;
+
+            if !poll(Duration::ZERO)? {
+                self.refresh();
+                self.render(columns)?;
+
+                if self.preview_is_stale() && !poll(Duration::ZERO)? {
+                    self.load_preview();
+                    self.render(columns)?;
+                }
+                let mut drawn_total = self.total();
+
+                while !poll(Duration::from_millis(100))? {
+                    if drawn_total != self.total() {
+                        drawn_total = self.total();
+                        self.render(columns)?;
+                    }
+                }
+            }
+
+            let step = match read()? {
+                Event::Key(key_event) => self.on_key(key_event),
+                Event::Paste(text) => {
+                    self.editor.insert(text);
+                    PickStep::Continue
+                }
+                _ => PickStep::Continue,
+            };
+
+            if step != PickStep::Continue {
+                return Ok(step);
+            }
+        }
+    }
+
+    fn total(&self) -> Option<usize> {
+        self.total.as_ref().and_then(|t| t.get())
+    }
+
+    pub fn refresh(&mut self) {
+        if self.query.as_deref() != Some(self.editor.line()) {
+            self.query = Some(self.editor.line().to_string());
+            self.tf.refresh();
+            self.matches.clear();
+            self.exhausted = false;
+            self.error = None;
+            self.highlighted = 0;
+            self.offset = 0;
+
+            let mut patterns = self.patterns.clone();
+            if !self.editor.line().is_empty() || patterns.is_empty() {
+                patterns.push(self.editor.line().to_string());
+            }
+
+            match self.search_mode.matcher(&patterns) {
+                Ok(search) => {
+                    self.total = Some(MatchCount::spawn_with(self.tf, search.clone()));
+                    self.search = Some(search);
+                }
+                Err(err) => {
+                    self.total = None;
+                    self.search = None;
+                    self.error = Some(err);
+                    self.exhausted = true;
+                }
+            }
+        }
+
+        self.fill(self.highlighted + self.rows);
+
+        self.highlighted = self.highlighted.min(self.matches.len().saturating_sub(1));
+        if self.highlighted < self.offset {
+            self.offset = self.highlighted;
+        } else if self.highlighted >= self.offset + self.rows {
+            self.offset = self.highlighted + 1 - self.rows;
+        }
+    }
+
+    fn fill(&mut self, wanted: usize) {
+        let search = match &self.search {
+            Some(search) => search.clone(),
+            None => return,
+        };
+
+        let matches = RefCell::new(std::mem::take(&mut self.matches));
+        let running = AtomicBool::new(true);
+
+        while !self.exhausted && matches.borrow().len() < wanted {
+            running.store(true, Ordering::Relaxed);
+
+            let find_result = self.tf.find_with(
+                &*search,
+                &|f| {
+                    matches.borrow_mut().push(f.clone());
+                    if matches.borrow().len() >= wanted {
+                        running.store(false, Ordering::Relaxed);
+                    }
+                },
+                &running,
+            );
+
+            match find_result {
+                FindResult::Error(err) => {
+                    self.error = Some(err);
+                    self.exhausted = true;
+                }
+                FindResult::Read => {}
+                FindResult::Eof => self.exhausted = true,
+            }
+        }
+
+        self.matches = matches.take();
+    }
+
+    pub fn on_key(&mut self, key_event: KeyEvent) -> PickStep {
+        if key_event.kind != KeyEventKind::Press {
+            return PickStep::Continue;
+        }
+
+        if terminal::is_cancel(&key_event) {
+            return PickStep::Abort;
+        }
+
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key_event.code {
+            KeyCode::Enter => return PickStep::Accept,
+            KeyCode::Up => self.move_by(-1),
+            KeyCode::Char('p') if ctrl => self.move_by(-1),
+            KeyCode::Down => self.move_by(1),
+            KeyCode::Char('n') if ctrl => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-(self.rows as isize)),
+            KeyCode::PageDown => self.move_by(self.rows as isize),
+            KeyCode::Tab => {
+                self.toggle();
+                self.move_by(1);
+            }
+            KeyCode::BackTab => {
+                self.toggle();
+                self.move_by(-1);
+            }
+            _ => {
+                self.editor.on_key(&key_event);
+            }
+        }
+
+        PickStep::Continue
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        self.highlighted = self.highlighted.saturating_add_signed(delta);
+        self.fill(self.highlighted + 1);
+        self.highlighted = self.highlighted.min(self.matches.len().saturating_sub(1));
+    }
+
+    fn toggle(&mut self) {
+        let node_name = match self.matches.get(self.highlighted) {
+            Some(node_name) => node_name,
+            None => return,
+        };
+
+        match self.marked.iter().position(|m| m == node_name) {
+            Some(i) => {
+                self.marked.remove(i);
+            }
+            None => self.marked.push(node_name.clone()),
+        }
+    }
+
+    pub fn selection(&self) -> Vec<String> {
+        if !self.marked.is_empty() {
+            return self.marked.clone();
+        }
+
+        self.matches
+            .get(self.highlighted)
+            .map(|m| vec![m.clone()])
+            .unwrap_or_default()
+    }
+
+    fn preview_is_stale(&self) -> bool {
+        let highlighted = self.matches.get(self.highlighted);
+
+        self.show_preview
+            && highlighted.is_some()
+            && self.preview.as_ref().map(|(p, _)| p) != highlighted
+    }
+
+    fn load_preview(&mut self) {
+        if let Some(node_name) = self.matches.get(self.highlighted) {
+            let lines = Preview::load(node_name, self.rows + 1).lines();
+            self.preview = Some((node_name.clone(), lines));
+        }
+    }
+
+    fn render(&self, columns: usize) -> io::Result<()> {
+        let mut tty = terminal::tty();
+
+        let list_columns = if self.show_preview && columns >= 40 {
+            columns / 2
+        } else {
+            columns
+        };
+
+        let status = match (&self.error, self.total()) {
+            (Some(err), _) => format!("  [ERR] {}", err),
+            (None, Some(total)) => format!(
+                "  {}/{} ({} selected)",
+                self.matches.len(),
+                total,
+                self.marked.len()
+            ),
+            (None, None) => format!(
+                "  {}/... ({} selected)",
+                self.matches.len(),
+                self.marked.len()
+            ),
+        };
+
+        queue!(tty, MoveTo(0, 1), Clear(ClearType::UntilNewLine))?;
+        write!(tty, "{}", fit(&status, columns))?;
+
+        for row in 0..self.rows {
+            let index = self.offset + row;
+            queue!(
+                tty,
+                MoveTo(0, (row + 2) as u16),
+                Clear(ClearType::UntilNewLine)
+            )?;
+
+            let node_name = match self.matches.get(index) {
+                Some(node_name) => node_name,
+                None => continue,
+            };
+
+            let cursor = if index == self.highlighted { '>' } else { ' ' };
+            let mark = if self.marked.contains(node_name) {
+                '*'
+            } else {
+                ' '
+            };
+            let line = fit(format!("{}{}{}", cursor, mark, node_name), list_columns);
+
+            if index == self.highlighted {
+                queue!(tty, SetAttribute(Attribute::Reverse))?;
+                write!(tty, "{}", line)?;
+                queue!(tty, SetAttribute(Attribute::Reset))?;
+            } else {
+                write!(tty, "{}", line)?;
+            }
+        }
+
+        if list_columns < columns {
+            self.render_preview(&mut *tty, list_columns, columns - list_columns)?;
+        }
+
+        let (visible, cursor) = self.editor.view(columns.saturating_sub(3));
+        queue!(tty, MoveTo(0, 0), Clear(ClearType::UntilNewLine))?;
+        write!(tty, "> {}", visible)?;
+        queue!(tty, MoveTo((cursor + 2) as u16, 0))?;
+
+        tty.flush()
+    }
+
+    fn render_preview(&self, tty: &mut impl Write, x: usize, columns: usize) -> io::Result<()> {
+        let highlighted = self.matches.get(self.highlighted);
+
+        let lines = match &self.preview {
+            Some((node_name, lines)) if Some(node_name) == highlighted => lines.as_slice(),
+            _ => &[],
+        };
+
+        for row in 0..=self.rows {
+            queue!(tty, MoveTo(x as u16, (row + 1) as u16))?;
+            let line = lines.get(row).map(String::as_str).unwrap_or_default();
+            write!(tty, "│ {}", fit(line, columns.saturating_sub(2)))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn fit<S: AsRef<str>>(text: S, columns: usize) -> String {
+    let mut width = 0;
+
+    text.as_ref()
+        .chars()
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width <= columns
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod picker_tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::{matcher::SearchMode, temp_file::TempFile};
+
+    use super::{fit, PickStep, Picker};
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn get_index() -> TempFile {
+        let mut tf = TempFile::new().unwrap();
+        for name in [
+            "./src/main.rs",
+            "./src/lib.rs",
+            "./README.md",
+            "./tests/integration_tests.rs",
+        ] {
+            tf.append(name).unwrap();
+        }
+        tf
+    }
+
+    #[test]
+    fn pick_highlighted() {
+        let mut tf = get_index();
+        let mut picker = Picker::new(&mut tf, "rs");
+        picker.refresh();
+
+        picker.on_key(key(KeyCode::Down));
+        assert_eq!(picker.on_key(key(KeyCode::Enter)), PickStep::Accept);
+        assert_eq!(picker.selection(), vec!["./src/lib.rs"]);
+    }
+
+    #[test]
+    fn empty_query_lists_the_index() {
+        let mut tf = get_index();
+        let mut picker = Picker::new(&mut tf, "");
+        picker.refresh();
+
+        assert_eq!(
+            picker.matches,
+            vec![
+                "./src/main.rs",
+                "./src/lib.rs",
+                "./README.md",
+                "./tests/integration_tests.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_patterns_join_the_query() {
+        let mut tf = get_index();
+        let mut picker = Picker::new(&mut tf, "").patterns(vec!["README".into(), "lib".into()]);
+        picker.refresh();
+        assert_eq!(picker.matches, vec!["./src/lib.rs", "./README.md"]);
+
+        let mut tf = get_index();
+        let mut picker = Picker::new(&mut tf, "src")
+            .patterns(vec![r"\.rs$".into()])
+            .search_mode(SearchMode {
+                match_all: true,
+                ..SearchMode::default()
+            });
+        picker.refresh();
+        assert_eq!(picker.matches, vec!["./src/main.rs", "./src/lib.rs"]);
+    }
+
+    #[test]
+    fn pick_marked_across_queries() {
+        let mut tf = get_index();
+        let mut picker = Picker::new(&mut tf, "src");
+        picker.refresh();
+
+        picker.on_key(key(KeyCode::Tab));
+        picker.on_key(key(KeyCode::Backspace));
+        picker.on_key(key(KeyCode::Backspace));
+        picker.on_key(key(KeyCode::Backspace));
+        for c in "README".chars() {
+            picker.on_key(key(KeyCode::Char(c)));
+        }
+        picker.refresh();
+        picker.on_key(key(KeyCode::Tab));
+        picker.on_key(key(KeyCode::Down));
+
+        assert_eq!(picker.selection(), vec!["./src/main.rs", "./README.md"]);
+        assert_eq!(picker.on_key(key(KeyCode::Esc)), PickStep::Abort);
+    }
+
+    #[test]
+    fn fit_to_columns() {
+        assert_eq!(fit("./src/main.rs", 6), "./src/");
+        assert_eq!(fit("./日本", 5), "./日");
+        assert_eq!(fit("abc", 10), "abc");
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fs, io::Read};
+
+const SNIFF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum Preview {
+    Text(Vec<String>),
+    Dir(Vec<String>),
+    Binary { size: u64, kind: &'static str },
+    Error(String),
+}
+
+impl Preview {
+    pub fn load<S: AsRef<str>>(node_name: S, max_lines: usize) -> Preview {
+        let node_name = node_name.as_ref();
+
+        let metadata = match fs::metadata(node_name) {
+            Ok(metadata) => metadata,
+            Err(err) => return Preview::Error(err.to_string()),
+        };
+
+        if metadata.is_dir() {
+            return Self::load_dir(node_name, max_lines);
+        }
+
+        let mut file = match fs::File::open(node_name) {
+            Ok(file) => file,
+            Err(err) => return Preview::Error(err.to_string()),
+        };
+
+        let mut head = Vec::with_capacity(SNIFF_SIZE);
+        if let Err(err) = file.by_ref().take(SNIFF_SIZE as u64).read_to_end(&mut head) {
+            return Preview::Error(err.to_string());
+        }
+
+        if head.contains(&0) {
+            return Preview::Binary {
+                size: metadata.len(),
+                kind: Self::kind(&head),
+            };
+        }
+
+        let text = String::from_utf8_lossy(&head);
+        let mut lines = text
+            .lines()
+            .take(max_lines)
+            .map(|l| {
+                l.replace('\t', "    ")
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>();
+
+        if metadata.len() > SNIFF_SIZE as u64 && lines.len() < max_lines {
+            lines.pop();
+        }
+
+        Preview::Text(lines)
+    }
+
+    fn load_dir(node_name: &str, max_lines: usize) -> Preview {
+        let dir = match fs::read_dir(node_name) {
+            Ok(dir) => dir,
+            Err(err) => return Preview::Error(err.to_string()),
+        };
+
+        let mut names = dir
+            .map_while(Result::ok)
+            .filter_map(|information| {
+                let name = information.file_name().into_string().ok()?;
+                match information.file_type() {
+                    Ok(file_type) if file_type.is_dir() => Some(format!("{}/", name)),
+                    _ => Some(name),
+                }
+            })
+            .collect::<Vec<String>>();
+
+        names.sort();
+        names.truncate(max_lines);
+
+        Preview::Dir(names)
+    }
+
+    fn kind(head: &[u8]) -> &'static str {
+        const MAGIC: [(&[u8], &str); 9] = [
+            (b"\x7fELF", "ELF executable"),
+            (b"MZ", "PE executable"),
+            (b"\x89PNG", "PNG image"),
+            (b"\xff\xd8\xff", "JPEG image"),
+            (b"GIF8", "GIF image"),
+            (b"%PDF", "PDF document"),
+            (b"PK\x03\x04", "zip archive"),
+            (b"\x1f\x8b", "gzip archive"),
+            (b"!<arch>", "ar archive"),
+        ];
+
+        MAGIC
+            .iter()
+            .find(|(magic, _)| head.starts_with(magic))
+            .map(|(_, kind)| *kind)
+            .unwrap_or("binary data")
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        match self {
+            Preview::Text(lines) | Preview::Dir(lines) => lines.clone(),
+            Preview::Binary { size, kind } => vec![format!("{}, {}", kind, human_size(*size))],
+            Preview::Error(err) => vec![format!("[ERR] {}", err)],
+        }
+    }
+}
+
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod preview_tests {
+    use crate::temp_file::TempFile;
+
+    use super::{human_size, Preview};
+
+    #[test]
+    fn preview_text_file() {
+        let mut tf = TempFile::new().unwrap();
+        tf.append("first\tline").unwrap();
+        tf.append("second").unwrap();
+        tf.append("third").unwrap();
+
+        assert_eq!(
+            Preview::load(&tf.name, 2),
+            Preview::Text(vec!["first    line".to_string(), "second".to_string()])
+        );
+    }
+
+    #[test]
+    fn preview_dir() {
+        let tf = TempFile::new().unwrap();
+        let dir = format!("{}.d", tf.name);
+        std::fs::create_dir_all(format!("{}/nested", dir)).unwrap();
+        std::fs::write(format!("{}/b.txt", dir), "").unwrap();
+        std::fs::write(format!("{}/a.txt", dir), "").unwrap();
+
+        let preview = Preview::load(&dir, 10);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            preview,
+            Preview::Dir(vec![
+                "a.txt".to_string(),
+                "b.txt".to_string(),
+                "nested/".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn preview_binary_file() {
+        let tf = TempFile::new().unwrap();
+        std::fs::write(&tf.name, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        let preview = Preview::load(&tf.name, 10);
+        assert_eq!(
+            preview,
+            Preview::Binary {
+                size: 16,
+                kind: "PNG image"
+            }
+        );
+        assert_eq!(preview.lines(), vec!["PNG image, 16 B"]);
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(human_size(10), "10 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MB");
+    }
+}
@@ -0,0 +1,362 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{
+        read, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers,
+    },
+    execute, queue,
+    terminal::{self as crossterm_terminal, Clear, ClearType},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    history::History,
+    line_editor::LineEditor,
+    terminal::{self, RawMode},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    Continue,
+    Submit(String),
+    Exit,
+}
+
+struct Search {
+    needle: String,
+    found: Option<usize>,
+}
+
+pub struct Prompt {
+    history: History,
+    editor: LineEditor,
+    saved: String,
+    history_pos: Option<usize>,
+    search: Option<Search>,
+}
+
+impl Prompt {
+    pub fn new(history: History) -> Prompt {
+        Prompt {
+            history,
+            editor: LineEditor::default(),
+            saved: String::new(),
+            history_pos: None,
+            search: None,
+        }
+    }
+
+    pub fn read_line(&mut self) -> Option<String> {
+        let _raw_mode = match RawMode::enable() {
+            Ok(r) => r,
+            Err(_) => return None,
+        };
+
+        self.read_events(read)
+    }
+
+    fn read_events<R: FnMut() -> io::Result<Event>>(&mut self, mut next: R) -> Option<String> {
+        self.editor.clear();
+        self.history_pos = None;
+        self.search = None;
+
+        // execute! names its writer twice, the guard has to be taken once
+        // or the second lock of the tty waits forever.
+        let mut tty = terminal::tty();
+        let _ = execute!(tty, EnableBracketedPaste);
+        drop(tty);
+
+        let mut step = Step::Continue;
+        while step == Step::Continue {
+            if self.render().is_err() {
+                step = Step::Exit;
+                break;
+            }
+
+            step = match next() {
+                Ok(Event::Key(key_event)) => self.on_key(key_event),
+                Ok(Event::Paste(text)) => self.on_paste(&text),
+                Ok(_) => Step::Continue,
+                Err(_) => Step::Exit,
+            };
+        }
+
+        let mut tty = terminal::tty();
+        let _ = execute!(tty, DisableBracketedPaste);
+        let _ = writeln!(tty);
+
+        match step {
+            Step::Submit(query) => {
+                self.history.push(&query);
+                Some(query)
+            }
+            _ => None,
+        }
+    }
+
+    fn render(&self) -> io::Result<()> {
+        let mut tty = terminal::tty();
+        let columns = match crossterm_terminal::size() {
+            Ok((c, _)) if c > 0 => c as usize,
+            _ => 80,
+        };
+
+        queue!(tty, MoveToColumn(0), Clear(ClearType::UntilNewLine))?;
+
+        match &self.search {
+            Some(search) => {
+                let found = search
+                    .found
+                    .map(|i| self.history.entries()[i].as_str())
+                    .unwrap_or_default();
+                write!(tty, "(reverse-i-search)`{}': {}", search.needle, found)?;
+            }
+            None => {
+                let prompt = "> ";
+                let (visible, cursor) =
+                    self.editor.view(columns.saturating_sub(prompt.width() + 1));
+                write!(tty, "{}{}", prompt, visible)?;
+                queue!(tty, MoveToColumn((prompt.width() + cursor) as u16))?;
+            }
+        }
+
+        tty.flush()
+    }
+
+    fn on_paste(&mut self, text: &str) -> Step {
+        match &mut self.search {
+            Some(search) => {
+                search.needle.push_str(text);
+                search.found = self
+                    .history
+                    .search(&search.needle, self.history.entries().len());
+            }
+            None => self.editor.insert(text),
+        }
+
+        Step::Continue
+    }
+
+    pub fn on_key(&mut self, key_event: KeyEvent) -> Step {
+        if key_event.kind != KeyEventKind::Press {
+            return Step::Continue;
+        }
+
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+
+        if self.search.is_some() {
+            return self.on_search_key(key_event, ctrl);
+        }
+
+        if terminal::is_cancel(&key_event) {
+            return Step::Exit;
+        }
+
+        match key_event.code {
+            KeyCode::Enter => return Step::Submit(self.editor.line().trim().to_string()),
+            KeyCode::Char('r') if ctrl => {
+                self.search = Some(Search {
+                    needle: String::new(),
+                    found: None,
+                });
+            }
+            KeyCode::Up => self.history_up(),
+            KeyCode::Down => self.history_down(),
+            _ => {
+                self.editor.on_key(&key_event);
+            }
+        }
+
+        Step::Continue
+    }
+
+    fn on_search_key(&mut self, key_event: KeyEvent, ctrl: bool) -> Step {
+        let search = match &mut self.search {
+            Some(search) => search,
+            None => return Step::Continue,
+        };
+
+        match key_event.code {
+            KeyCode::Char('r') if ctrl => {
+                let before = search.found.unwrap_or(self.history.entries().len());
+                if let Some(found) = self.history.search(&search.needle, before) {
+                    search.found = Some(found);
+                }
+            }
+            KeyCode::Char('g') | KeyCode::Char('c') if ctrl => self.search = None,
+            KeyCode::Esc => self.search = None,
+            KeyCode::Char(c) if !ctrl => {
+                search.needle.push(c);
+                search.found = self
+                    .history
+                    .search(&search.needle, self.history.entries().len());
+            }
+            KeyCode::Backspace => {
+                search.needle.pop();
+                search.found = self
+                    .history
+                    .search(&search.needle, self.history.entries().len());
+            }
+            KeyCode::Enter => {
+                self.accept_search();
+                return Step::Submit(self.editor.line().trim().to_string());
+            }
+            _ => self.accept_search(),
+        }
+
+        Step::Continue
+    }
+
+    fn accept_search(&mut self) {
+        if let Some(Search {
+            found: Some(found), ..
+        }) = self.search.take()
+        {
+            self.editor.set_line(&self.history.entries()[found]);
+            self.history_pos = Some(found);
+        }
+    }
+
+    fn history_up(&mut self) {
+        let pos = match self.history_pos {
+            Some(0) => 0,
+            Some(pos) => pos - 1,
+            None if self.history.entries().is_empty() => return,
+            None => {
+                self.saved = self.editor.line().to_string();
+                self.history.entries().len() - 1
+            }
+        };
+
+        self.history_pos = Some(pos);
+        self.editor.set_line(&self.history.entries()[pos]);
+    }
+
+    fn history_down(&mut self) {
+        match self.history_pos {
+            Some(pos) if pos + 1 < self.history.entries().len() => {
+                self.history_pos = Some(pos + 1);
+                self.editor.set_line(&self.history.entries()[pos + 1]);
+            }
+            Some(_) => {
+                self.history_pos = None;
+                self.editor.set_line(std::mem::take(&mut self.saved));
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod prompt_tests {
+    use std::{collections::VecDeque, io};
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::{history::History, temp_file::TempFile};
+
+    use super::{Prompt, Step};
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn type_str(prompt: &mut Prompt, s: &str) {
+        for c in s.chars() {
+            assert_eq!(prompt.on_key(key(KeyCode::Char(c))), Step::Continue);
+        }
+    }
+
+    fn get_prompt(file: &TempFile) -> Prompt {
+        let mut history = History::from_file(&file.name, "/project");
+        history.push("src/main.rs");
+        history.push("tests");
+        history.push("src/lib.rs");
+        Prompt::new(history)
+    }
+
+    #[test]
+    fn recall_with_up_and_down() {
+        let file = TempFile::new().unwrap();
+        let mut prompt = get_prompt(&file);
+
+        type_str(&mut prompt, "draft");
+        prompt.on_key(key(KeyCode::Up));
+        prompt.on_key(key(KeyCode::Up));
+        assert_eq!(prompt.editor.line(), "tests");
+        prompt.on_key(key(KeyCode::Up));
+        prompt.on_key(key(KeyCode::Up));
+        assert_eq!(prompt.editor.line(), "src/main.rs");
+        prompt.on_key(key(KeyCode::Down));
+        prompt.on_key(key(KeyCode::Down));
+        prompt.on_key(key(KeyCode::Down));
+
+        assert_eq!(
+            prompt.on_key(key(KeyCode::Enter)),
+            Step::Submit("draft".to_string())
+        );
+    }
+
+    #[test]
+    fn reverse_search() {
+        let file = TempFile::new().unwrap();
+        let mut prompt = get_prompt(&file);
+
+        prompt.on_key(ctrl('r'));
+        type_str(&mut prompt, "src");
+        prompt.on_key(ctrl('r'));
+
+        assert_eq!(
+            prompt.on_key(key(KeyCode::Enter)),
+            Step::Submit("src/main.rs".to_string())
+        );
+    }
+
+    #[test]
+    fn cancel_reverse_search_keeps_line() {
+        let file = TempFile::new().unwrap();
+        let mut prompt = get_prompt(&file);
+
+        type_str(&mut prompt, "abc");
+        prompt.on_key(ctrl('r'));
+        type_str(&mut prompt, "tests");
+        prompt.on_key(key(KeyCode::Esc));
+        assert_eq!(prompt.editor.line(), "abc");
+
+        assert_eq!(prompt.on_key(key(KeyCode::Esc)), Step::Exit);
+    }
+
+    #[test]
+    fn read_line_through_tty() {
+        let file = TempFile::new().unwrap();
+        let mut prompt = get_prompt(&file);
+
+        let mut events = VecDeque::from([
+            Event::Key(key(KeyCode::Char('a'))),
+            Event::Paste("b/c".to_string()),
+            Event::Key(key(KeyCode::Enter)),
+        ]);
+        let next = || {
+            events
+                .pop_front()
+                .ok_or(io::ErrorKind::UnexpectedEof.into())
+        };
+
+        assert_eq!(prompt.read_events(next), Some("ab/c".to_string()));
+        assert_eq!(prompt.history.entries().last().unwrap(), "ab/c");
+
+        let mut events = VecDeque::from([Event::Key(key(KeyCode::Esc))]);
+        let next = || {
+            events
+                .pop_front()
+                .ok_or(io::ErrorKind::UnexpectedEof.into())
+        };
+        assert_eq!(prompt.read_events(next), None);
+    }
+}
@@ -1,73 +1,193 @@
-use regex::Regex;
+use regex::{Regex, RegexSet};
 use std::io::BufRead;
 
-#[derive(Default)]
+use crate::error::{Error, Result};
+
+// The set answers check() in one pass, the single regexes are kept for
+// match ranges. A set over the size limit falls back to the single ones.
+#[derive(Default, Clone)]
 pub struct RegexHelper {
     regexes: Vec<Regex>,
+    set: Option<RegexSet>,
+    all: bool,
+    segments: bool,
 }
 
 impl RegexHelper {
-    pub fn from_string<S: AsRef<str>>(pattern: S) -> Result<RegexHelper, String> {
-        let r = match Regex::new(pattern.as_ref()) {
-            Ok(r) => r,
-            Err(err) => return Err(err.to_string()),
-        };
+    fn new(regexes: Vec<Regex>) -> RegexHelper {
+        let set = RegexSet::new(regexes.iter().map(Regex::as_str)).ok();
+
+        RegexHelper {
+            regexes,
+            set,
+            all: false,
+            segments: false,
+        }
+    }
+
+    pub fn from_string<S: AsRef<str>>(pattern: S) -> Result<RegexHelper> {
+        Self::from_patterns(&[pattern])
+    }
+
+    pub fn from_patterns<S: AsRef<str>>(patterns: &[S]) -> Result<RegexHelper> {
+        let mut regexes = Vec::new();
+
+        for pattern in patterns {
+            match Regex::new(pattern.as_ref()) {
+                Ok(r) => regexes.push(r),
+                Err(err) => {
+                    return Err(Error::InvalidPattern {
+                        pattern: pattern.as_ref().to_string(),
+                        message: err.to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(Self::new(regexes))
+    }
+
+    // Each pattern has to cover a whole path segment, the slashes around it
+    // are left out of the match ranges.
+    pub fn from_segment_patterns<S: AsRef<str>>(patterns: &[S]) -> Result<RegexHelper> {
+        let segments = Self::from_patterns(patterns)?
+            .regexes
+            .iter()
+            .map(|r| format!("(?:^|/)(?:{})(?:/|$)", r.as_str()))
+            .collect::<Vec<String>>();
+
+        let mut helper = Self::from_patterns(&segments)?;
+        helper.segments = true;
+        Ok(helper)
+    }
 
-        Ok(RegexHelper { regexes: vec![r] })
+    pub fn match_all(mut self, all: bool) -> Self {
+        self.all = all;
+        self
     }
 
     pub fn from_gitignore<P: AsRef<str>>(dir: P) -> RegexHelper {
         let path = std::path::Path::new(dir.as_ref()).join(".gitignore");
 
-        let file = match std::fs::File::open(&path) {
-            Ok(f) => f,
-            Err(_) => return RegexHelper::default(),
-        };
+        Self::from_ignore_file(&path).unwrap_or_default()
+    }
+
+    pub fn from_ignore_file<P: AsRef<std::path::Path>>(path: P) -> Result<RegexHelper> {
+        let file = std::fs::File::open(path.as_ref())
+            .map_err(|err| Error::io(path.as_ref().to_string_lossy(), err))?;
 
         let lines = std::io::BufReader::new(file)
             .lines()
-            .map_while(Result::ok)
+            .map_while(std::io::Result::ok)
+            .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
             .collect::<Vec<String>>();
 
-        let mut regexes = lines
+        Ok(Self::from_globs(&lines))
+    }
+
+    pub fn from_globs<S: AsRef<str>>(lines: &[S]) -> RegexHelper {
+        let regexes = lines
             .iter()
-            .map(|s| {
-                s.replace("**", "$$$")
-                    .replace("*", "[^/]*")
-                    .replace(".", "\\.")
-                    .replace("$$$", ".*")
-                    .replace("?", ".")
-            })
+            .map(|s| Self::glob_regex(s.as_ref().trim_matches('/')))
             .filter_map(|p| regex::Regex::new(&p).ok())
             .collect::<Vec<Regex>>();
 
-        regexes.push(Regex::new(".git").unwrap());
+        Self::new(regexes)
+    }
+
+    // A glob matches whole path segments: "target" ignores ./target and
+    // ./a/target/b but not ./targets.rs.
+    fn glob_regex(glob: &str) -> String {
+        let mut regex = String::from("(?:^|/)");
+        let mut chars = glob.chars().peekable();
 
-        RegexHelper { regexes }
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    regex.push_str(".*");
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+
+        regex.push_str("(?:/|$)");
+        regex
+    }
+
+    pub fn extend(&mut self, other: RegexHelper) {
+        let mut regexes = std::mem::take(&mut self.regexes);
+        regexes.extend(other.regexes);
+        let segments = self.segments;
+        *self = Self::new(regexes).match_all(self.all);
+        self.segments = segments;
     }
 
     pub fn check<S: AsRef<str>>(&self, str: S) -> bool {
-        for r in &self.regexes {
-            if r.is_match(str.as_ref()) {
-                return true;
-            }
+        if self.all {
+            return !self.is_empty() && self.matches(str).len() == self.regexes.len();
+        }
+
+        match &self.set {
+            Some(set) => set.is_match(str.as_ref()),
+            None => self.regexes.iter().any(|r| r.is_match(str.as_ref())),
+        }
+    }
+
+    pub fn matches<S: AsRef<str>>(&self, str: S) -> Vec<usize> {
+        match &self.set {
+            Some(set) => set.matches(str.as_ref()).into_iter().collect(),
+            None => (0..self.regexes.len())
+                .filter(|i| self.regexes[*i].is_match(str.as_ref()))
+                .collect(),
         }
+    }
 
-        false
+    pub fn find_ranges<S: AsRef<str>>(&self, str: S) -> Vec<std::ops::Range<usize>> {
+        let mut ranges = self
+            .regexes
+            .iter()
+            .flat_map(|r| r.find_iter(str.as_ref()).map(|m| m.range()))
+            .map(|range| match self.segments {
+                true => Self::trim_slashes(str.as_ref(), range),
+                false => range,
+            })
+            .filter(|range| !range.is_empty())
+            .collect::<Vec<_>>();
+
+        ranges.sort_by_key(|range| range.start);
+        ranges
     }
 
     pub fn is_empty(&self) -> bool {
         self.regexes.is_empty()
     }
+
+    fn trim_slashes(str: &str, mut range: std::ops::Range<usize>) -> std::ops::Range<usize> {
+        if str[range.clone()].starts_with('/') {
+            range.start += 1;
+        }
+        if range.start < range.end && str[range.clone()].ends_with('/') {
+            range.end -= 1;
+        }
+        range
+    }
 }
 
 #[cfg(test)]
 mod ignore_files_tests {
-    use crate::regex_helper::RegexHelper;
+    use crate::{error::Error, regex_helper::RegexHelper};
 
     #[test]
     fn check_gitignore() {
-        let ignore = RegexHelper::from_gitignore(".");
+        let dir = std::env::temp_dir().join(format!("rfind_gitignore_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".gitignore"), "target/\n/local_data\n").unwrap();
+
+        let ignore = RegexHelper::from_gitignore(dir.to_str().unwrap());
+        _ = std::fs::remove_dir_all(&dir);
 
         assert!(ignore.check("haha/target"));
         assert!(ignore.check("hihi/target"));
@@ -82,7 +202,7 @@ mod ignore_files_tests {
         let ignore = match RegexHelper::from_string(".*some") {
             Ok(i) => i,
             Err(err) => {
-                assert_eq!(err, "");
+                assert_eq!(err.to_string(), "");
                 return;
             }
         };
@@ -91,4 +211,70 @@ mod ignore_files_tests {
         assert!(ignore.check("asdgoasogaosomesome"));
         assert!(!ignore.check("soahasme"));
     }
+
+    #[test]
+    fn invalid_pattern_error() {
+        match RegexHelper::from_string("(") {
+            Err(Error::InvalidPattern { pattern, .. }) => assert_eq!(pattern, "("),
+            _ => panic!("expected an invalid pattern error"),
+        }
+    }
+
+    #[test]
+    fn check_extra_globs() {
+        let mut ignore = RegexHelper::default();
+        ignore.extend(RegexHelper::from_globs(&["*.log", "node_modules/"]));
+
+        assert!(ignore.check("./app/debug.log"));
+        assert!(ignore.check("./web/node_modules"));
+        assert!(!ignore.check("./src/main.rs"));
+        assert!(!ignore.check("./app/debug.logger"));
+        assert!(!ignore.check("./web/node_modules_old"));
+
+        let ignore = RegexHelper::from_globs(&["a+b/**/c?.txt"]);
+        assert!(ignore.check("./a+b/x/y/c1.txt"));
+        assert!(!ignore.check("./aab/x/c1.txt"));
+    }
+
+    #[test]
+    fn report_which_patterns_hit() {
+        let search = RegexHelper::from_patterns(&["main", r"\.rs$", "test"]).unwrap();
+
+        assert_eq!(search.matches("./src/main.rs"), vec![0, 1]);
+        assert!(search.check("./tests/it.txt"));
+        assert!(!search.check("./README.md"));
+
+        let search = search.match_all(true);
+        assert!(!search.check("./src/main.rs"));
+        assert!(search.check("./tests/main_test.rs"));
+
+        assert!(matches!(
+            RegexHelper::from_patterns(&["ok", "("]),
+            Err(Error::InvalidPattern { pattern, .. }) if pattern == "("
+        ));
+    }
+
+    #[test]
+    fn find_match_ranges() {
+        let search = RegexHelper::from_string("a.").unwrap();
+
+        assert_eq!(search.find_ranges("./abc/xaz"), vec![2..4, 7..9]);
+        assert!(search.find_ranges("./src").is_empty());
+    }
+
+    #[test]
+    fn whole_segment_patterns() {
+        let search = RegexHelper::from_segment_patterns(&["Attributes", r"\w+\.cs"]).unwrap();
+
+        assert!(search.check("./SomeApi/Attributes"));
+        assert!(search.check("./SomeApi/Attributes/Some.cs"));
+        assert!(!search.check("./SomeApi/UseAttributesHelper.txt"));
+        assert_eq!(search.find_ranges("./Api/Attributes"), vec![6..16]);
+        assert_eq!(search.find_ranges("./Api/Some.cs"), vec![6..13]);
+
+        assert!(matches!(
+            RegexHelper::from_segment_patterns(&["("]),
+            Err(Error::InvalidPattern { pattern, .. }) if pattern == "("
+        ));
+    }
 }
@@ -0,0 +1,24 @@
+pub fn init_script<S: AsRef<str>>(shell: S) -> Option<&'static str> {
+    match shell.as_ref() {
+        "bash" => Some(include_str!("shell/rfind.bash")),
+        "zsh" => Some(include_str!("shell/rfind.zsh")),
+        "fish" => Some(include_str!("shell/rfind.fish")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod shell_tests {
+    use super::init_script;
+
+    #[test]
+    fn scripts_use_the_picker() {
+        for shell in ["bash", "zsh", "fish"] {
+            let script = init_script(shell).unwrap();
+            assert!(script.contains("rfind --pick"));
+            assert!(script.contains("rfind --pick --dirs"));
+        }
+
+        assert!(init_script("tcsh").is_none());
+    }
+}
@@ -0,0 +1,57 @@
+# rfind key bindings and completion for bash
+# eval "$(rfind --shell-init bash)"
+
+__rfind_select() {
+  local item
+  rfind --pick "$@" | while IFS= read -r item; do
+    printf '%q ' "$item"
+  done
+}
+
+__rfind_file_widget() {
+  local selected
+  selected="$(__rfind_select -p=.)"
+  READLINE_LINE="${READLINE_LINE:0:$READLINE_POINT}$selected${READLINE_LINE:$READLINE_POINT}"
+  READLINE_POINT=$((READLINE_POINT + ${#selected}))
+}
+
+__rfind_cd_widget() {
+  local dir
+  dir="$(rfind --pick --dirs -p=.)" && [ -n "$dir" ] && builtin cd -- "$dir"
+}
+
+__rfind_default_completion="$(complete -p -D 2> /dev/null | sed -n 's/.* -F \([^ ]*\) .*/\1/p')"
+
+__rfind_completion() {
+  local cur="${COMP_WORDS[COMP_CWORD]}"
+
+  if [[ "$cur" != *'**' ]]; then
+    if [ -n "$__rfind_default_completion" ]; then
+      "$__rfind_default_completion" "$@"
+      return
+    fi
+    return 0
+  fi
+
+  local base="${cur%\*\*}" root=. query=""
+  if [ -n "$base" ] && [ -d "$base" ]; then
+    root="${base%/}"
+    [ -z "$root" ] && root=/
+  else
+    query="$base"
+  fi
+
+  local selected
+  selected="$(__rfind_select -p="$root" "$query")"
+  if [ -n "$selected" ]; then
+    COMPREPLY=("${selected% }")
+  fi
+  printf '\e[5n'
+}
+
+complete -D -F __rfind_completion -o bashdefault -o default
+
+bind -m emacs-standard -x '"\C-t": __rfind_file_widget'
+bind -m vi-insert -x '"\C-t": __rfind_file_widget'
+bind -m emacs-standard -x '"\ec": __rfind_cd_widget'
+bind -m vi-insert -x '"\ec": __rfind_cd_widget'
@@ -0,0 +1,53 @@
+# rfind key bindings and completion for fish
+# rfind --shell-init fish | source
+
+function __rfind_select
+    rfind --pick $argv | while read -l item
+        printf '%s ' (string escape -- $item)
+    end
+end
+
+function rfind-file-widget
+    commandline -i -- (__rfind_select -p=.)
+    commandline -f repaint
+end
+
+function rfind-cd-widget
+    set -l dir (rfind --pick --dirs -p=.)
+    if test -n "$dir"
+        cd -- $dir
+    end
+    commandline -f repaint
+end
+
+function rfind-completion
+    set -l token (commandline -t)
+    if not string match -q -- '*\*\*' $token
+        commandline -f complete
+        return
+    end
+
+    set -l base (string replace -r '\*\*$' '' -- $token)
+    set -l root .
+    set -l query $base
+    if test -n "$base"; and test -d "$base"
+        set root (string replace -r '/$' '' -- $base)
+        test -z "$root"; and set root /
+        set query ''
+    end
+
+    set -l selected (__rfind_select -p=$root $query)
+    if test -n "$selected"
+        commandline -t -- $selected
+    end
+    commandline -f repaint
+end
+
+bind \ct rfind-file-widget
+bind \ec rfind-cd-widget
+bind \t rfind-completion
+if bind -M insert > /dev/null 2>&1
+    bind -M insert \ct rfind-file-widget
+    bind -M insert \ec rfind-cd-widget
+    bind -M insert \t rfind-completion
+end
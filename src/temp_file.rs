@@ -1,12 +1,21 @@
 use std::{
     fs::{File, OpenOptions},
-    io::{self, Read, Seek},
+    io::{self, BufRead, Read, Seek, Write},
     path::{Path, PathBuf},
-    sync::atomic::{AtomicU64, Ordering},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use crate::regex_helper::RegexHelper;
+use crate::{
+    diagnostics,
+    error::{Error, Result},
+    matcher::Matcher,
+    regex_helper::RegexHelper,
+};
 
 static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
 
@@ -24,16 +33,71 @@ const FILE_FLAG_DELETE_ON_CLOSE: u32 = 0x0400_0000;
 
 #[derive(PartialEq)]
 pub enum FindResult {
-    Error(String),
+    Error(Error),
     Read,
     Eof,
 }
 
+pub struct MatchCount {
+    running: Arc<AtomicBool>,
+    total: Arc<Mutex<Option<usize>>>,
+}
+
+impl MatchCount {
+    pub fn spawn<S: AsRef<str>>(tf: &TempFile, pattern: S) -> MatchCount {
+        match RegexHelper::from_string(pattern) {
+            Ok(searcher) => Self::spawn_with(tf, searcher),
+            Err(_) => MatchCount {
+                running: Arc::new(AtomicBool::new(false)),
+                total: Arc::new(Mutex::new(None)),
+            },
+        }
+    }
+
+    pub fn spawn_with<M: Matcher + 'static>(tf: &TempFile, matcher: M) -> MatchCount {
+        let running = Arc::new(AtomicBool::new(true));
+        let total = Arc::new(Mutex::new(None));
+
+        if let Ok(read) = TempFile::open_temp_read_file(Path::new(&tf.name)) {
+            let running = running.clone();
+            let total = total.clone();
+
+            thread::spawn(move || {
+                let mut count = 0;
+                for line in io::BufReader::new(read).lines() {
+                    if !running.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    match line {
+                        Ok(line) if matcher.is_match(&line) => count += 1,
+                        Ok(_) => {}
+                        Err(_) => return,
+                    }
+                }
+                *total.lock().unwrap() = Some(count);
+            });
+        }
+
+        MatchCount { running, total }
+    }
+
+    pub fn get(&self) -> Option<usize> {
+        *self.total.lock().unwrap()
+    }
+}
+
+impl Drop for MatchCount {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
 pub struct TempFile {
     pub name: String,
     pub write: Option<File>,
     read: File,
     read_seek: u64,
+    removed: Vec<String>,
 }
 
 impl Drop for TempFile {
@@ -43,25 +107,30 @@ impl Drop for TempFile {
 }
 
 impl TempFile {
-    pub fn new() -> Result<TempFile, String> {
-        let dir = std::env::temp_dir();
-        let temp_file_path = Self::create_unique_temp_file_path(&dir);
+    pub fn new() -> Result<TempFile> {
+        Self::new_in(std::env::temp_dir())
+    }
+
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> Result<TempFile> {
+        let temp_file_path = Self::create_unique_temp_file_path(dir.as_ref());
+        let name = temp_file_path.to_string_lossy().into_owned();
 
         let to_write = match Self::create_temp_write_file(&temp_file_path) {
             Ok(f) => f,
-            Err(_) => return Err("cant create write-only temp file".to_string()),
+            Err(err) => return Err(Error::io(name, err)),
         };
 
         let to_read = match Self::open_temp_read_file(&temp_file_path) {
             Ok(f) => f,
-            Err(_) => return Err("cant open temp file as read-only".to_string()),
+            Err(err) => return Err(Error::io(name, err)),
         };
 
         Ok(TempFile {
-            name: temp_file_path.to_string_lossy().into_owned(),
+            name,
             write: Some(to_write),
             read: to_read,
             read_seek: 0,
+            removed: Vec::new(),
         })
     }
 
@@ -113,53 +182,145 @@ impl TempFile {
 
     pub fn refresh(&mut self) {
         self.read_seek = 0;
+
+        if let Err(err) = self.compact() {
+            diagnostics::report(&Error::io(&self.name, err));
+        }
+    }
+
+    pub fn append<S: AsRef<str>>(&mut self, node_name: S) -> Result<()> {
+        self.compact()
+            .and_then(|_| {
+                let mut to_write = self.writer()?;
+                to_write.seek(io::SeekFrom::End(0))?;
+                to_write.write_all(format!("{}\n", node_name.as_ref()).as_bytes())
+            })
+            .map_err(|err| Error::io(&self.name, err))
+    }
+
+    pub fn remove<S: AsRef<str>>(&mut self, node_name: S) {
+        self.removed.push(node_name.as_ref().to_string());
+    }
+
+    fn writer(&self) -> io::Result<&File> {
+        match &self.write {
+            Some(write_f) => Ok(write_f),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "temp file is read-only",
+            )),
+        }
+    }
+
+    fn is_removed(&self, node_name: &str) -> bool {
+        self.removed.iter().any(|r| {
+            node_name
+                .strip_prefix(r.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    fn compact(&mut self) -> io::Result<()> {
+        if self.removed.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        self.read.seek(io::SeekFrom::Start(0))?;
+        self.read.read_to_string(&mut content)?;
+
+        let kept =
+            content
+                .lines()
+                .filter(|l| !self.is_removed(l))
+                .fold(String::new(), |mut acc, l| {
+                    acc.push_str(l);
+                    acc.push('\n');
+                    acc
+                });
+
+        let mut to_write = self.writer()?;
+        to_write.set_len(0)?;
+        to_write.seek(io::SeekFrom::Start(0))?;
+        to_write.write_all(kept.as_bytes())?;
+
+        self.removed.clear();
+        self.read_seek = 0;
+
+        Ok(())
     }
 
     pub fn find<F: Fn(&String), S: AsRef<str>>(&mut self, pattern: S, on_find: &F) -> FindResult {
+        self.find_while(pattern, on_find, &AtomicBool::new(true))
+    }
+
+    pub fn find_while<F: Fn(&String), S: AsRef<str>>(
+        &mut self,
+        pattern: S,
+        on_find: &F,
+        running: &AtomicBool,
+    ) -> FindResult {
+        match RegexHelper::from_string(pattern) {
+            Ok(searcher) => self.find_with(&searcher, on_find, running),
+            Err(err) => FindResult::Error(err),
+        }
+    }
+
+    pub fn find_with<M: Matcher + ?Sized, F: Fn(&String)>(
+        &mut self,
+        matcher: &M,
+        on_find: &F,
+        running: &AtomicBool,
+    ) -> FindResult {
+        let chunk_start = self.read_seek;
+
         match self.read.seek(io::SeekFrom::Start(self.read_seek)) {
             Ok(_) => {}
-            Err(err) => return FindResult::Error(err.to_string()),
-        };
-
-        let searcher = match RegexHelper::from_string(pattern) {
-            Ok(s) => s,
-            Err(err) => return FindResult::Error(err),
+            Err(err) => return FindResult::Error(Error::io(&self.name, err)),
         };
 
         const SIZE: usize = 128 * 1024;
 
         let mut buf = vec![0; SIZE];
 
-        match self.read.read(&mut buf) {
-            Ok(read) => {
-                self.read_seek += read as u64;
-                if read < 1 {
-                    return FindResult::Eof;
-                }
-            }
+        let read = match self.read.read(&mut buf) {
+            Ok(read) if read < 1 => return FindResult::Eof,
+            Ok(read) => read,
             Err(err) => {
-                return FindResult::Error(err.to_string());
+                return FindResult::Error(Error::io(&self.name, err));
             }
         };
 
+        // Only whole lines are searched, a cut line (or a cut character) is
+        // read again from its start by the next call.
+        buf.truncate(read);
+        if let Some(end) = buf.iter().rposition(|b| *b == b'\n') {
+            buf.truncate(end + 1);
+        }
+        self.read_seek += buf.len() as u64;
+
         let str = match String::from_utf8(buf) {
             Ok(str) => str,
             Err(err) => {
-                return FindResult::Error(err.to_string());
+                return FindResult::Error(Error::IndexCorrupted {
+                    path: self.name.clone(),
+                    message: err.to_string(),
+                });
             }
         };
 
-        let splitted = str.split("\n");
-        let mut last: &str = "";
-        for s in splitted {
-            if searcher.check(s) {
-                on_find(&s.to_string());
+        let mut consumed = 0;
+        for s in str.split_inclusive('\n') {
+            if !running.load(Ordering::Relaxed) {
+                self.read_seek = chunk_start + consumed as u64;
+                return FindResult::Read;
             }
-            last = s;
-        }
+            consumed += s.len();
 
-        if !last.is_empty() && !last.ends_with('\0') && !last.ends_with('\n') {
-            self.read_seek -= last.len() as u64;
+            let line = s.trim_end_matches('\n');
+            if !line.is_empty() && matcher.is_match(line) {
+                on_find(&line.to_string());
+            }
         }
 
         FindResult::Read
@@ -168,7 +329,13 @@ impl TempFile {
 
 #[cfg(test)]
 mod temp_file_tests {
-    use super::TempFile;
+    use std::{
+        cell::RefCell,
+        sync::atomic::{AtomicBool, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use super::{FindResult, MatchCount, TempFile};
 
     #[test]
     fn temp_files_use_unique_names() {
@@ -189,4 +356,92 @@ mod temp_file_tests {
 
         assert!(!std::path::Path::new(&file_name).exists());
     }
+
+    #[test]
+    fn append_and_remove_nodes() {
+        let mut temp_file = TempFile::new().unwrap();
+        temp_file.append("./a/one.txt").unwrap();
+        temp_file.append("./a/b/two.txt").unwrap();
+        temp_file.append("./a/bc/three.txt").unwrap();
+
+        temp_file.remove("./a/b");
+        temp_file.append("./a/four.txt").unwrap();
+        temp_file.refresh();
+
+        let found = RefCell::new(Vec::new());
+        while temp_file.find("a/", &|f| found.borrow_mut().push(f.clone())) == FindResult::Read {}
+
+        assert_eq!(
+            found.take(),
+            vec!["./a/one.txt", "./a/bc/three.txt", "./a/four.txt"]
+        );
+    }
+
+    #[test]
+    fn stopped_find_resumes_from_the_same_line() {
+        let mut temp_file = TempFile::new().unwrap();
+        temp_file.append("./one.txt").unwrap();
+        temp_file.append("./two.txt").unwrap();
+
+        let running = AtomicBool::new(true);
+        let found = RefCell::new(Vec::new());
+        let find_result = temp_file.find_while(
+            "txt",
+            &|f| {
+                found.borrow_mut().push(f.clone());
+                running.store(false, Ordering::Relaxed);
+            },
+            &running,
+        );
+        assert!(find_result == FindResult::Read);
+        assert_eq!(*found.borrow(), vec!["./one.txt"]);
+
+        while temp_file.find("txt", &|f| found.borrow_mut().push(f.clone())) == FindResult::Read {}
+
+        assert_eq!(found.take(), vec!["./one.txt", "./two.txt"]);
+    }
+
+    #[test]
+    fn count_matches_in_background() {
+        let mut temp_file = TempFile::new().unwrap();
+        for i in 0..1000 {
+            temp_file.append(format!("./dir/{}.txt", i)).unwrap();
+        }
+
+        let count = MatchCount::spawn(&temp_file, "1");
+        let start = Instant::now();
+        while count.get().is_none() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(count.get(), Some(271));
+    }
+
+    #[test]
+    fn find_with_own_matcher() {
+        let mut temp_file = TempFile::new().unwrap();
+        temp_file.append("./src/main.rs").unwrap();
+        temp_file.append("./README.md").unwrap();
+
+        let owned = |line: &str| line.starts_with("./src/");
+        let found = RefCell::new(Vec::new());
+        let running = AtomicBool::new(true);
+        while temp_file.find_with(&owned, &|f| found.borrow_mut().push(f.clone()), &running)
+            == FindResult::Read
+        {}
+
+        assert_eq!(found.take(), vec!["./src/main.rs"]);
+    }
+
+    #[test]
+    fn empty_query_lists_only_entries() {
+        let mut temp_file = TempFile::new().unwrap();
+        temp_file.append("./src/main.rs").unwrap();
+        temp_file.append("./README.md").unwrap();
+
+        let found = RefCell::new(Vec::new());
+        while temp_file.find("", &|f| found.borrow_mut().push(f.clone())) == FindResult::Read {}
+
+        assert_eq!(found.take(), vec!["./src/main.rs", "./README.md"]);
+    }
 }
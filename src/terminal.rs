@@ -0,0 +1,147 @@
+use std::{
+    io::{self, LineWriter, Write},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard, OnceLock,
+    },
+    time::Duration,
+};
+
+use crossterm::{
+    event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    style::{ResetColor, SetForegroundColor},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+
+use crate::config::ColorScheme;
+
+static TTY: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
+pub fn tty() -> MutexGuard<'static, Box<dyn Write + Send>> {
+    TTY.get_or_init(|| Mutex::new(open_tty()))
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(windows)]
+pub const TTY_PATH: &str = "CONOUT$";
+#[cfg(not(windows))]
+pub const TTY_PATH: &str = "/dev/tty";
+
+fn open_tty() -> Box<dyn Write + Send> {
+    match std::fs::OpenOptions::new().write(true).open(TTY_PATH) {
+        Ok(f) => Box::new(LineWriter::new(f)),
+        Err(_) => Box::new(io::stderr()),
+    }
+}
+
+pub struct RawMode {}
+
+impl RawMode {
+    pub fn enable() -> io::Result<RawMode> {
+        enable_raw_mode()?;
+        Ok(RawMode {})
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+pub fn is_cancel(key_event: &KeyEvent) -> bool {
+    key_event.kind == KeyEventKind::Press
+        && (key_event.code == KeyCode::Esc
+            || (key_event.code == KeyCode::Char('c')
+                && key_event.modifiers.contains(KeyModifiers::CONTROL)))
+}
+
+pub fn wait_for_cancel(running: &AtomicBool, cancelled: &AtomicBool) {
+    while running.load(Ordering::Relaxed) {
+        match poll(Duration::from_millis(50)) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(_) => return,
+        }
+
+        match read() {
+            Ok(Event::Key(key_event)) if is_cancel(&key_event) => {
+                cancelled.store(true, Ordering::Relaxed);
+                running.store(false, Ordering::Relaxed);
+            }
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+pub fn highlight(text: &str, ranges: &[Range<usize>], colors: &ColorScheme) -> String {
+    let mut result = format!("{}", SetForegroundColor(colors.path));
+    let mut at = 0;
+
+    for range in ranges {
+        if range.start < at {
+            continue;
+        }
+
+        result.push_str(&text[at..range.start]);
+        result.push_str(&format!(
+            "{}{}{}",
+            SetForegroundColor(colors.matched),
+            &text[range.clone()],
+            SetForegroundColor(colors.path)
+        ));
+        at = range.end;
+    }
+
+    result.push_str(&text[at..]);
+    result.push_str(&format!("{}", ResetColor));
+
+    result
+}
+
+pub fn numbered(index: usize, colors: &ColorScheme) -> String {
+    format!(
+        "{}{}){}",
+        SetForegroundColor(colors.index),
+        index,
+        ResetColor
+    )
+}
+
+#[cfg(test)]
+mod terminal_tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{highlight, is_cancel};
+    use crate::config::ColorScheme;
+
+    #[test]
+    fn cancel_keys() {
+        assert!(is_cancel(&KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(is_cancel(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL
+        )));
+        assert!(!is_cancel(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::NONE
+        )));
+        assert!(!is_cancel(&KeyEvent::new(
+            KeyCode::Enter,
+            KeyModifiers::NONE
+        )));
+    }
+
+    #[test]
+    fn highlight_matches() {
+        let colors = ColorScheme::default();
+
+        assert_eq!(
+            highlight("./src/main.rs", &[2..5, 6..10], &colors),
+            "\x1b[39m./\x1b[38;5;9msrc\x1b[39m/\x1b[38;5;9mmain\x1b[39m.rs\x1b[0m"
+        );
+    }
+}
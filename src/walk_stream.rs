@@ -0,0 +1,221 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::{
+    entry::Entry,
+    error::{Error, Result},
+    ignore_rules::IgnoreRules,
+    matcher::IgnoreFilter,
+    path_style,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkCancel(Arc<AtomicBool>);
+
+impl WalkCancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone)]
+struct Walk {
+    root: Arc<str>,
+    ignore: Arc<dyn IgnoreFilter>,
+    limit: Arc<Semaphore>,
+    sender: mpsc::Sender<Result<Entry>>,
+    cancel: WalkCancel,
+}
+
+// Entries of every directory come out in the order they were read, but
+// directories are read concurrently, so the overall order is not stable.
+// Dropping the stream cancels the walk as well.
+pub struct WalkStream {
+    entries: mpsc::Receiver<Result<Entry>>,
+    cancel: WalkCancel,
+}
+
+impl WalkStream {
+    pub fn spawn<I: IgnoreFilter + 'static, S: AsRef<str>>(
+        full_path: S,
+        ignore: I,
+        concurrency: usize,
+    ) -> Self {
+        let root = path_style::normalize(full_path);
+        let (sender, entries) = mpsc::channel(1024);
+        let cancel = WalkCancel::default();
+
+        let ignore: Arc<dyn IgnoreFilter> = if ignore.is_empty() {
+            Arc::new(IgnoreRules::default().build(&root))
+        } else {
+            Arc::new(ignore)
+        };
+
+        let walk = Walk {
+            root: root.as_str().into(),
+            ignore,
+            limit: Arc::new(Semaphore::new(concurrency.max(1))),
+            sender,
+            cancel: cancel.clone(),
+        };
+        tokio::spawn(walk.read_nodes(root, 0));
+
+        WalkStream { entries, cancel }
+    }
+
+    pub fn canceller(&self) -> WalkCancel {
+        self.cancel.clone()
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancel.cancel();
+        self.entries.close();
+    }
+
+    pub async fn recv(&mut self) -> Option<Result<Entry>> {
+        if self.cancel.is_cancelled() {
+            return None;
+        }
+        self.entries.recv().await
+    }
+}
+
+impl Stream for WalkStream {
+    type Item = Result<Entry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.cancel.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        self.entries.poll_recv(cx)
+    }
+}
+
+impl Walk {
+    fn read_nodes(
+        self,
+        full_path: String,
+        depth: usize,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async move {
+            let _permit = match self.limit.acquire().await {
+                Ok(permit) => permit,
+                Err(_) => return,
+            };
+
+            if self.cancel.is_cancelled() {
+                return;
+            }
+
+            let mut dir = match tokio::fs::read_dir(&full_path).await {
+                Ok(dir) => dir,
+                Err(err) => {
+                    _ = self
+                        .sender
+                        .send(Err(Error::read_dir(&full_path, err)))
+                        .await;
+                    return;
+                }
+            };
+
+            while let Ok(Some(information)) = dir.next_entry().await {
+                if self.cancel.is_cancelled() {
+                    return;
+                }
+
+                let file_type = match information.file_type().await {
+                    Ok(file_type) => file_type,
+                    Err(_) => continue,
+                };
+
+                let file_name = match information.file_name().into_string() {
+                    Ok(n) => n,
+                    Err(_) => continue,
+                };
+
+                let node_path = path_style::join(&full_path, &file_name);
+
+                if self.ignore.is_ignored(&node_path, file_type.is_dir())
+                    || !(file_type.is_file() || file_type.is_dir())
+                {
+                    continue;
+                }
+
+                if file_type.is_dir() {
+                    tokio::spawn(self.clone().read_nodes(node_path.clone(), depth + 1));
+                }
+
+                let entry = Entry::new(&*self.root, node_path, depth + 1, file_type);
+                if self.sender.send(Ok(entry)).await.is_err() {
+                    return;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod walk_stream_tests {
+    use std::{cell::RefCell, future::poll_fn, pin::Pin};
+
+    use futures_core::Stream;
+
+    use super::WalkStream;
+    use crate::{error::Error, regex_helper::RegexHelper, walker::Walker};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn stream_yields_the_same_entries_as_walk() {
+        let ignore = RegexHelper::default();
+        let mut stream = WalkStream::spawn(".", ignore.clone(), 4);
+
+        let mut streamed = Vec::new();
+        while let Some(entry) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            let entry = entry.unwrap();
+            if entry.is_file() {
+                streamed.push(entry.path().to_string());
+            }
+        }
+
+        let walked = RefCell::new(Vec::new());
+        Walker::walk(
+            ".",
+            &|entry| walked.borrow_mut().push(entry.path().to_string()),
+            &ignore,
+        )
+        .unwrap();
+
+        let mut walked = walked.take();
+        streamed.sort();
+        walked.sort();
+        assert!(streamed.contains(&"./src/main.rs".to_string()));
+        assert_eq!(streamed, walked);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn cancel_and_report_errors() {
+        let ignore = RegexHelper::default();
+
+        let mut stream = WalkStream::spawn(".", ignore.clone(), 2);
+        assert!(stream.recv().await.is_some());
+        stream.canceller().cancel();
+        assert!(stream.recv().await.is_none());
+
+        let mut stream = WalkStream::spawn("./no/such/dir", ignore, 2);
+        assert!(matches!(stream.recv().await, Some(Err(Error::Io { .. }))));
+        assert!(stream.recv().await.is_none());
+    }
+}
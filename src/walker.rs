@@ -1,28 +1,137 @@
-use std::{fs, io};
+use std::{fs, path::PathBuf};
 
-use crate::regex_helper::RegexHelper;
+use crate::{
+    diagnostics,
+    entry::Entry,
+    error::{Error, Result},
+    ignore_rules::IgnoreRules,
+    matcher::IgnoreFilter,
+    path_style,
+    walk_stream::WalkStream,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WalkControl {
+    Continue,
+    SkipDir,
+    Stop,
+}
+
+impl From<()> for WalkControl {
+    fn from(_: ()) -> WalkControl {
+        WalkControl::Continue
+    }
+}
 
 #[derive(Default)]
 pub struct Walker {}
 
 impl Walker {
-    pub fn walk<F: Fn(&String), S: AsRef<str>>(
+    pub const CONCURRENCY: usize = 64;
+
+    pub fn walk<F: Fn(&Entry) -> R, R: Into<WalkControl>, S: AsRef<str>>(
         full_path: S,
         on_file: &F,
-        ignore: &RegexHelper,
-    ) -> io::Result<()> {
+        ignore: &dyn IgnoreFilter,
+    ) -> Result<()> {
+        Self::walk_with_errors(full_path, on_file, &diagnostics::report, ignore)
+    }
+
+    pub fn walk_with_errors<F, R, E, S>(
+        full_path: S,
+        on_file: &F,
+        on_error: &E,
+        ignore: &dyn IgnoreFilter,
+    ) -> Result<()>
+    where
+        F: Fn(&Entry) -> R,
+        R: Into<WalkControl>,
+        E: Fn(&Error),
+        S: AsRef<str>,
+    {
+        let root = path_style::normalize(full_path);
+        Self::walk_nodes(&root, &root, 0, on_file, on_error, ignore, false).map(|_| ())
+    }
+
+    pub fn dedup_roots<S: AsRef<str>>(roots: &[S]) -> Vec<String> {
+        let roots = roots
+            .iter()
+            .map(|root| {
+                let root = match root.as_ref().trim_end_matches('/') {
+                    "" => "/",
+                    trimmed => trimmed,
+                };
+                let canonical = fs::canonicalize(root).unwrap_or_else(|_| PathBuf::from(root));
+                (root.to_string(), canonical)
+            })
+            .collect::<Vec<(String, PathBuf)>>();
+
+        roots
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, path))| {
+                !roots.iter().enumerate().any(|(j, (_, other))| {
+                    if other == path {
+                        j < *i
+                    } else {
+                        path.starts_with(other)
+                    }
+                })
+            })
+            .map(|(_, (root, _))| root.clone())
+            .collect()
+    }
+
+    pub fn walk_dirs<F: Fn(&Entry) -> R, R: Into<WalkControl>, S: AsRef<str>>(
+        full_path: S,
+        on_dir: &F,
+        ignore: &dyn IgnoreFilter,
+    ) -> Result<()> {
+        Self::walk_dirs_with_errors(full_path, on_dir, &diagnostics::report, ignore)
+    }
+
+    pub fn walk_dirs_with_errors<F, R, E, S>(
+        full_path: S,
+        on_dir: &F,
+        on_error: &E,
+        ignore: &dyn IgnoreFilter,
+    ) -> Result<()>
+    where
+        F: Fn(&Entry) -> R,
+        R: Into<WalkControl>,
+        E: Fn(&Error),
+        S: AsRef<str>,
+    {
+        let root = path_style::normalize(full_path);
+        Self::walk_nodes(&root, &root, 0, on_dir, on_error, ignore, true).map(|_| ())
+    }
+
+    fn walk_nodes<F, R, E, S>(
+        root: &str,
+        full_path: S,
+        depth: usize,
+        on_node: &F,
+        on_error: &E,
+        ignore: &dyn IgnoreFilter,
+        dirs: bool,
+    ) -> Result<WalkControl>
+    where
+        F: Fn(&Entry) -> R,
+        R: Into<WalkControl>,
+        E: Fn(&Error),
+        S: AsRef<str>,
+    {
         let read_result = fs::read_dir(full_path.as_ref());
 
         let dir = match read_result {
             Ok(dir) => dir,
-            Err(msg) => {
-                println!("[ERR] {:?} err={:?}", full_path.as_ref(), msg);
-                return Ok(());
-            }
+            Err(err) => return Err(Error::read_dir(&full_path, err)),
         };
 
+        let default_ignore;
         let ignore = if ignore.is_empty() {
-            &RegexHelper::from_gitignore(&full_path)
+            default_ignore = IgnoreRules::default().build(&full_path);
+            &default_ignore
         } else {
             ignore
         };
@@ -43,68 +152,113 @@ impl Walker {
                 Err(_) => continue,
             };
 
-            let full_path = &format!("{}/{}", full_path.as_ref(), file_name);
+            let full_path = &path_style::join(&full_path, &file_name);
 
-            let ignore_node = ignore.check(full_path);
+            let ignore_node = ignore.is_ignored(full_path, file_type.is_dir());
 
             if ignore_node {
                 continue;
             }
 
             if file_type.is_file() {
-                on_file(full_path);
+                if !dirs {
+                    match on_node(&Entry::new(root, full_path.clone(), depth + 1, file_type)).into()
+                    {
+                        WalkControl::Continue => {}
+                        WalkControl::SkipDir => return Ok(WalkControl::Continue),
+                        WalkControl::Stop => return Ok(WalkControl::Stop),
+                    }
+                }
             } else if file_type.is_dir() {
-                Self::walk(full_path, on_file, ignore)?;
+                if dirs {
+                    match on_node(&Entry::new(root, full_path.clone(), depth + 1, file_type)).into()
+                    {
+                        WalkControl::Continue => {}
+                        WalkControl::SkipDir => continue,
+                        WalkControl::Stop => return Ok(WalkControl::Stop),
+                    }
+                }
+
+                let walked =
+                    Self::walk_nodes(root, full_path, depth + 1, on_node, on_error, ignore, dirs);
+                match walked {
+                    Ok(WalkControl::Stop) => return Ok(WalkControl::Stop),
+                    Ok(_) => {}
+                    Err(err) => on_error(&err),
+                }
             }
         }
 
-        Ok(())
+        Ok(WalkControl::Continue)
     }
 
-    pub async fn walk_async<F: Fn(&String), S: AsRef<str>>(
+    pub fn stream<I: IgnoreFilter + 'static, S: AsRef<str>>(
         full_path: S,
-        on_file: &F,
-        ignore: &RegexHelper,
-    ) -> io::Result<()> {
-        let read_result = tokio::fs::read_dir(full_path.as_ref()).await;
-
-        let mut dir = match read_result {
-            Ok(dir) => dir,
-            Err(msg) => {
-                println!("[ERR] {:?} err={:?}", full_path.as_ref(), msg);
-                return Ok(());
-            }
-        };
+        ignore: I,
+        concurrency: usize,
+    ) -> WalkStream {
+        WalkStream::spawn(full_path, ignore, concurrency)
+    }
 
-        let ignore = if ignore.is_empty() {
-            &RegexHelper::from_gitignore(&full_path)
-        } else {
-            ignore
-        };
+    pub async fn walk_async<F, R, I, S>(full_path: S, on_file: &F, ignore: I) -> Result<()>
+    where
+        F: Fn(&Entry) -> R,
+        R: Into<WalkControl>,
+        I: IgnoreFilter + 'static,
+        S: AsRef<str>,
+    {
+        Self::walk_async_with_errors(full_path, on_file, &diagnostics::report, ignore).await
+    }
 
-        while let Ok(Some(information)) = dir.next_entry().await {
-            let file_type = match information.file_type().await {
-                Ok(file_type) => file_type,
-                Err(_) => continue,
-            };
+    pub async fn walk_async_with_errors<F, R, E, I, S>(
+        full_path: S,
+        on_file: &F,
+        on_error: &E,
+        ignore: I,
+    ) -> Result<()>
+    where
+        F: Fn(&Entry) -> R,
+        R: Into<WalkControl>,
+        E: Fn(&Error),
+        I: IgnoreFilter + 'static,
+        S: AsRef<str>,
+    {
+        let root = path_style::normalize(full_path);
+        let mut stream = Self::stream(&root, ignore, Self::CONCURRENCY);
+        let mut skipped: Vec<String> = Vec::new();
 
-            let file_name = match information.file_name().into_string() {
-                Ok(n) => n,
-                Err(_) => continue,
+        while let Some(entry) = stream.recv().await {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) if err.path() == Some(root.as_str()) => return Err(err),
+                Err(err) => {
+                    on_error(&err);
+                    continue;
+                }
             };
 
-            let full_path = &format!("{}/{}", full_path.as_ref(), file_name);
-
-            let ignore_node = ignore.check(full_path);
+            let in_skipped = skipped.iter().any(|dir| {
+                entry
+                    .path()
+                    .strip_prefix(dir.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            });
 
-            if ignore_node {
+            if !entry.is_file() || in_skipped {
                 continue;
             }
 
-            if file_type.is_file() {
-                on_file(full_path);
-            } else if file_type.is_dir() {
-                Box::pin(Self::walk_async(full_path, on_file, ignore)).await?;
+            match on_file(&entry).into() {
+                WalkControl::Continue => {}
+                WalkControl::SkipDir => {
+                    if let Some((dir, _)) = entry.path().rsplit_once('/') {
+                        skipped.push(dir.to_string());
+                    }
+                }
+                WalkControl::Stop => {
+                    stream.cancel();
+                    break;
+                }
             }
         }
 
@@ -114,11 +268,14 @@ impl Walker {
 
 #[cfg(test)]
 mod walker_tests {
-    use std::cell::RefCell;
+    use std::{
+        cell::RefCell,
+        path::{Path, PathBuf},
+    };
 
-    use crate::regex_helper::RegexHelper;
+    use crate::{error::Error, regex_helper::RegexHelper};
 
-    use super::Walker;
+    use super::{WalkControl, Walker};
 
     #[test]
     fn simple_walk() {
@@ -128,7 +285,7 @@ mod walker_tests {
         _ = Walker::walk(
             "..",
             &|name| {
-                if search.check(name) {
+                if search.check(name.path()) {
                     has_been_found.replace(true);
                 }
             },
@@ -138,6 +295,132 @@ mod walker_tests {
         assert!(has_been_found.take());
     }
 
+    #[test]
+    fn walk_dirs() {
+        let ignore = RegexHelper::default();
+        let found = RefCell::new(Vec::new());
+        _ = Walker::walk_dirs(
+            ".",
+            &|entry| found.borrow_mut().push(entry.path().to_string()),
+            &ignore,
+        );
+
+        assert!(found.borrow().contains(&"./src".to_string()));
+        assert!(!found.borrow().contains(&"./src/main.rs".to_string()));
+    }
+
+    #[test]
+    fn dedup_nested_roots() {
+        assert_eq!(
+            Walker::dedup_roots(&["src", "./src/", "tests", ".", "tests"]),
+            vec!["."]
+        );
+        assert_eq!(
+            Walker::dedup_roots(&["src/", "tests", "./src", "missing"]),
+            vec!["src", "tests", "missing"]
+        );
+    }
+
+    #[test]
+    fn walk_reports_missing_root() {
+        let ignore = RegexHelper::default();
+        let errors = RefCell::new(Vec::new());
+        let result = Walker::walk_with_errors(
+            "./no/such/dir",
+            &|_| {},
+            &|err| errors.borrow_mut().push(err.to_string()),
+            &ignore,
+        );
+
+        assert!(matches!(result, Err(Error::Io { path, .. }) if path == "./no/such/dir"));
+        assert!(errors.borrow().is_empty());
+    }
+
+    // The filter is asked before a directory is read, removing it there
+    // makes the read below the root fail.
+    fn vanishing_dir(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rfind_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(root.join("gone")).unwrap();
+        std::fs::write(root.join("kept.txt"), "").unwrap();
+        root
+    }
+
+    fn remove_gone(path: &str, is_dir: bool) -> bool {
+        if is_dir && path.ends_with("/gone") {
+            _ = std::fs::remove_dir(path);
+        }
+        false
+    }
+
+    fn gone(root: &Path) -> String {
+        root.join("gone").to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn errors_below_the_root_go_to_the_callback() {
+        let root = vanishing_dir("walk_dirs_errors");
+        let errors = RefCell::new(Vec::new());
+        let found = RefCell::new(Vec::new());
+        let walked = Walker::walk_dirs_with_errors(
+            root.to_str().unwrap(),
+            &|entry| found.borrow_mut().push(entry.path().to_string()),
+            &|err: &Error| errors.borrow_mut().extend(err.path().map(String::from)),
+            &remove_gone,
+        );
+        _ = std::fs::remove_dir_all(&root);
+
+        assert!(walked.is_ok());
+        assert_eq!(*found.borrow(), vec![gone(&root)]);
+        assert_eq!(*errors.borrow(), vec![gone(&root)]);
+    }
+
+    #[test]
+    fn stop_and_skip_dirs() {
+        let ignore = RegexHelper::default();
+
+        let found = RefCell::new(Vec::new());
+        _ = Walker::walk(
+            ".",
+            &|entry| {
+                found.borrow_mut().push(entry.path().to_string());
+                WalkControl::Stop
+            },
+            &ignore,
+        );
+        assert_eq!(found.borrow().len(), 1);
+
+        let found = RefCell::new(Vec::new());
+        _ = Walker::walk_dirs(
+            ".",
+            &|entry| {
+                found.borrow_mut().push(entry.path().to_string());
+                if entry.path() == "./src" {
+                    WalkControl::SkipDir
+                } else {
+                    WalkControl::Continue
+                }
+            },
+            &ignore,
+        );
+        assert!(found.borrow().contains(&"./src".to_string()));
+        assert!(!found.borrow().iter().any(|p| p.starts_with("./src/")));
+    }
+
+    #[test]
+    fn excluded_dirs_prune_subtrees() {
+        let ignore = RegexHelper::from_globs(&["src"]);
+        let found = RefCell::new(Vec::new());
+        Walker::walk(
+            ".",
+            &|entry| found.borrow_mut().push(entry.path().to_string()),
+            &ignore,
+        )
+        .unwrap();
+
+        assert!(found.borrow().contains(&"./Cargo.toml".to_string()));
+        assert!(!found.borrow().iter().any(|p| p.starts_with("./src/")));
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn walk_async() {
         let ignore = RegexHelper::default();
@@ -146,14 +429,51 @@ mod walker_tests {
         _ = Walker::walk_async(
             "..",
             &|name| {
-                if search.check(name) {
+                if search.check(name.path()) {
                     has_been_found.replace(true);
                 }
             },
-            &ignore,
+            ignore,
         )
         .await;
 
         assert!(has_been_found.take());
     }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn walk_async_stops() {
+        let ignore = RegexHelper::default();
+        let found = RefCell::new(0);
+        let walked = Walker::walk_async(
+            ".",
+            &|_| {
+                *found.borrow_mut() += 1;
+                WalkControl::Stop
+            },
+            ignore,
+        )
+        .await;
+
+        assert!(walked.is_ok());
+        assert_eq!(*found.borrow(), 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn walk_async_reports_errors_below_the_root() {
+        let root = vanishing_dir("walk_async_errors");
+        let errors = RefCell::new(Vec::new());
+        let found = RefCell::new(Vec::new());
+        let walked = Walker::walk_async_with_errors(
+            root.to_str().unwrap(),
+            &|entry| found.borrow_mut().push(entry.path().to_string()),
+            &|err: &Error| errors.borrow_mut().extend(err.path().map(String::from)),
+            remove_gone,
+        )
+        .await;
+        _ = std::fs::remove_dir_all(&root);
+
+        assert!(walked.is_ok());
+        assert_eq!(found.borrow().len(), 1);
+        assert_eq!(*errors.borrow(), vec![gone(&root)]);
+    }
 }
@@ -0,0 +1,449 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread::JoinHandle,
+};
+
+use crate::{ignore_rules::IgnoreRules, matcher::IgnoreFilter, path_style};
+
+#[derive(Debug, PartialEq)]
+pub enum WatchEvent {
+    Created(String),
+    Removed(String),
+}
+
+// Dropping the watcher stops its thread and closes the inotify handle.
+pub struct Watcher {
+    events: mpsc::Receiver<WatchEvent>,
+    dirs: platform::Dirs,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    pub fn new<I: IgnoreFilter + 'static, S: AsRef<str>>(
+        full_path: S,
+        ignore: I,
+    ) -> io::Result<Watcher> {
+        Self::spawn(full_path, ignore, true)
+    }
+
+    // Watches the root only, the rest is added by walking with filter(),
+    // so the tree is read once and no directory is read before it is
+    // watched.
+    pub fn root_only<I: IgnoreFilter + 'static, S: AsRef<str>>(
+        full_path: S,
+        ignore: I,
+    ) -> io::Result<Watcher> {
+        Self::spawn(full_path, ignore, false)
+    }
+
+    fn spawn<I: IgnoreFilter + 'static, S: AsRef<str>>(
+        full_path: S,
+        ignore: I,
+        recursive: bool,
+    ) -> io::Result<Watcher> {
+        let ignore: Arc<dyn IgnoreFilter> = if ignore.is_empty() {
+            Arc::new(IgnoreRules::default().build(&full_path))
+        } else {
+            Arc::new(ignore)
+        };
+
+        let (sender, events) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+
+        let (dirs, thread) = platform::spawn(
+            path_style::normalize(full_path),
+            ignore,
+            sender,
+            recursive,
+            running.clone(),
+        )?;
+
+        Ok(Watcher {
+            events,
+            dirs,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    // An ignore filter that also watches every directory it lets through,
+    // walkers ask it before reading a directory.
+    pub fn filter(&self, ignore: Arc<dyn IgnoreFilter>) -> WatchDirs {
+        WatchDirs {
+            ignore,
+            dirs: self.dirs.clone(),
+        }
+    }
+
+    pub fn drain(&self) -> Vec<WatchEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+
+pub struct WatchDirs {
+    ignore: Arc<dyn IgnoreFilter>,
+    dirs: platform::Dirs,
+}
+
+impl IgnoreFilter for WatchDirs {
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let ignored = self.ignore.is_ignored(path, is_dir);
+
+        if is_dir && !ignored {
+            self.dirs.watch(path);
+        }
+
+        ignored
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::{
+        collections::HashMap,
+        fs, io,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::Sender,
+            Arc, Mutex,
+        },
+        thread::{self, JoinHandle},
+        time::Duration,
+    };
+
+    use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+
+    use super::WatchEvent;
+    use crate::{diagnostics, error::Error, matcher::IgnoreFilter, path_style};
+
+    struct Tree {
+        watches: Watches,
+        dirs: HashMap<WatchDescriptor, String>,
+        ignore: Arc<dyn IgnoreFilter>,
+        sender: Sender<WatchEvent>,
+        failed: usize,
+    }
+
+    #[derive(Clone)]
+    pub struct Dirs(Arc<Mutex<Tree>>);
+
+    impl Dirs {
+        pub fn watch(&self, full_path: &str) {
+            self.0.lock().unwrap().watch(full_path);
+        }
+    }
+
+    impl Tree {
+        fn mask() -> WatchMask {
+            WatchMask::CREATE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO
+                | WatchMask::ONLYDIR
+        }
+
+        // Only the first failure is shown, the inotify limit would
+        // otherwise print a line for every directory left.
+        fn watch(&mut self, full_path: &str) -> bool {
+            match self.watches.add(full_path, Self::mask()) {
+                Ok(wd) => {
+                    self.dirs.insert(wd, full_path.to_string());
+                    true
+                }
+                Err(err) => {
+                    if self.failed == 0 {
+                        diagnostics::warn(format!(
+                            "directories are not watched {}",
+                            Error::io(full_path, err)
+                        ));
+                    }
+                    self.failed += 1;
+                    false
+                }
+            }
+        }
+
+        fn add(&mut self, full_path: &str, report_files: bool) {
+            if !self.watch(full_path) {
+                return;
+            }
+
+            let dir = match fs::read_dir(full_path) {
+                Ok(dir) => dir,
+                Err(_) => return,
+            };
+
+            for information in dir.map_while(Result::ok) {
+                let file_type = match information.file_type() {
+                    Ok(file_type) => file_type,
+                    Err(_) => continue,
+                };
+
+                let file_name = match information.file_name().into_string() {
+                    Ok(n) => n,
+                    Err(_) => continue,
+                };
+
+                let full_path = path_style::join(full_path, &file_name);
+
+                if self.ignore.is_ignored(&full_path, file_type.is_dir()) {
+                    continue;
+                }
+
+                if file_type.is_file() {
+                    if report_files {
+                        let _ = self.sender.send(WatchEvent::Created(full_path));
+                    }
+                } else if file_type.is_dir() {
+                    self.add(&full_path, report_files);
+                }
+            }
+        }
+
+        // A directory moved out of the tree keeps sending events from its
+        // new place, so its watches go away with it.
+        fn forget(&mut self, full_path: &str) {
+            let gone = self
+                .dirs
+                .iter()
+                .filter(|(_, dir)| {
+                    dir.strip_prefix(full_path)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                })
+                .map(|(wd, _)| wd.clone())
+                .collect::<Vec<WatchDescriptor>>();
+
+            for wd in gone {
+                self.dirs.remove(&wd);
+                _ = self.watches.remove(wd);
+            }
+        }
+
+        fn on_event(&mut self, wd: &WatchDescriptor, mask: EventMask, name: &str) -> bool {
+            if mask.contains(EventMask::IGNORED) {
+                self.dirs.remove(wd);
+                return true;
+            }
+
+            let dir = match self.dirs.get(wd) {
+                Some(dir) => dir,
+                None => return true,
+            };
+
+            let full_path = path_style::join(dir, name);
+            let is_dir = mask.contains(EventMask::ISDIR);
+
+            if is_dir && mask.contains(EventMask::MOVED_FROM) {
+                self.forget(&full_path);
+            }
+
+            if self.ignore.is_ignored(&full_path, is_dir) {
+                return true;
+            }
+
+            let event = if mask.intersects(EventMask::CREATE | EventMask::MOVED_TO) {
+                if is_dir {
+                    self.add(&full_path, true);
+                    return true;
+                }
+                WatchEvent::Created(full_path)
+            } else if mask.intersects(EventMask::DELETE | EventMask::MOVED_FROM) {
+                WatchEvent::Removed(full_path)
+            } else {
+                return true;
+            };
+
+            self.sender.send(event).is_ok()
+        }
+    }
+
+    pub fn spawn(
+        full_path: String,
+        ignore: Arc<dyn IgnoreFilter>,
+        sender: Sender<WatchEvent>,
+        recursive: bool,
+        running: Arc<AtomicBool>,
+    ) -> io::Result<(Dirs, JoinHandle<()>)> {
+        let mut inotify = Inotify::init()?;
+
+        let mut tree = Tree {
+            watches: inotify.watches(),
+            dirs: HashMap::new(),
+            ignore,
+            sender,
+            failed: 0,
+        };
+
+        if recursive {
+            tree.add(&full_path, false);
+        } else {
+            tree.watch(&full_path);
+        }
+
+        if tree.dirs.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, full_path));
+        }
+
+        let dirs = Dirs(Arc::new(Mutex::new(tree)));
+        let tree = dirs.clone();
+
+        let thread = thread::spawn(move || {
+            let mut buffer = [0; 4096];
+
+            while running.load(Ordering::Relaxed) {
+                let events = match inotify.read_events(&mut buffer) {
+                    Ok(events) => events,
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(50));
+                        continue;
+                    }
+                    Err(_) => return,
+                };
+
+                let mut tree = tree.0.lock().unwrap();
+                for event in events {
+                    let name = event.name.and_then(|n| n.to_str()).unwrap_or_default();
+
+                    if !tree.on_event(&event.wd, event.mask, name) {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok((dirs, thread))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::{
+        io,
+        sync::{atomic::AtomicBool, mpsc::Sender, Arc},
+        thread::JoinHandle,
+    };
+
+    use super::WatchEvent;
+    use crate::matcher::IgnoreFilter;
+
+    #[derive(Clone)]
+    pub struct Dirs;
+
+    impl Dirs {
+        pub fn watch(&self, _full_path: &str) {}
+    }
+
+    pub fn spawn(
+        _full_path: String,
+        _ignore: Arc<dyn IgnoreFilter>,
+        _sender: Sender<WatchEvent>,
+        _recursive: bool,
+        _running: Arc<AtomicBool>,
+    ) -> io::Result<(Dirs, JoinHandle<()>)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file watching is only supported on linux",
+        ))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod watcher_tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{regex_helper::RegexHelper, temp_file::TempFile};
+
+    use super::{WatchEvent, Watcher};
+
+    fn wait_for(watcher: &Watcher, count: usize) -> Vec<WatchEvent> {
+        let start = Instant::now();
+        let mut events = Vec::new();
+        while events.len() < count && start.elapsed() < Duration::from_secs(5) {
+            events.extend(watcher.drain());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        events
+    }
+
+    #[test]
+    fn watch_created_and_removed_files() {
+        let dir = TempFile::new().unwrap();
+        let root = format!("{}.d", dir.name);
+        std::fs::create_dir_all(format!("{}/nested", root)).unwrap();
+
+        let ignore = RegexHelper::from_string("ignored").unwrap();
+        let watcher = Watcher::new(&root, ignore).unwrap();
+
+        std::fs::write(format!("{}/ignored.txt", root), "").unwrap();
+        std::fs::write(format!("{}/nested/new.txt", root), "").unwrap();
+        std::fs::rename(
+            format!("{}/nested/new.txt", root),
+            format!("{}/renamed.txt", root),
+        )
+        .unwrap();
+
+        let events = wait_for(&watcher, 3);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::Created(format!("{}/nested/new.txt", root)),
+                WatchEvent::Removed(format!("{}/nested/new.txt", root)),
+                WatchEvent::Created(format!("{}/renamed.txt", root)),
+            ]
+        );
+    }
+
+    #[test]
+    fn walking_with_the_filter_adds_watches() {
+        let dir = TempFile::new().unwrap();
+        let root = format!("{}.d", dir.name);
+        let outside = format!("{}.out", dir.name);
+        std::fs::create_dir_all(format!("{}/a/b", root)).unwrap();
+
+        let ignore = RegexHelper::from_string("ignored").unwrap();
+        let watcher = Watcher::root_only(&root, ignore.clone()).unwrap();
+        crate::walker::Walker::walk(&root, &|_| {}, &watcher.filter(std::sync::Arc::new(ignore)))
+            .unwrap();
+
+        std::fs::write(format!("{}/a/b/new.txt", root), "").unwrap();
+        assert_eq!(
+            wait_for(&watcher, 1),
+            vec![WatchEvent::Created(format!("{}/a/b/new.txt", root))]
+        );
+
+        // Events from a directory moved out are not reported anymore.
+        std::fs::rename(format!("{}/a", root), &outside).unwrap();
+        std::fs::write(format!("{}/b/late.txt", outside), "").unwrap();
+        std::fs::write(format!("{}/top.txt", root), "").unwrap();
+
+        let events = wait_for(&watcher, 2);
+        drop(watcher);
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_dir_all(&outside).unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::Removed(format!("{}/a", root)),
+                WatchEvent::Created(format!("{}/top.txt", root)),
+            ]
+        );
+    }
+}
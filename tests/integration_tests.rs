@@ -2,7 +2,8 @@ use std::cell::RefCell;
 
 use rfind::{
     envs::Envs,
-    find_mode::FindMode,
+    find_mode::{FindMode, Ignores},
+    finder::Finder,
     regex_helper::RegexHelper,
     temp_file::{FindResult, TempFile},
     walker::Walker,
@@ -28,8 +29,8 @@ fn search_pattern() {
     Walker::walk(
             env.start_path,
             &|file| {
-                if checker.check(file) {
-                    assert_eq!(*file, r"./src/main.rs".to_string());
+                if checker.check(file.path()) {
+                    assert_eq!(file.path(), r"./src/main.rs");
                     has_been_found.replace(true);
                 };
             },
@@ -40,6 +41,23 @@ fn search_pattern() {
     assert!(has_been_found.take())
 }
 
+#[test]
+fn finder_iterator() {
+    let words = get_env_2();
+    let env = Envs::new(&words);
+
+    let found = Finder::new(env.start_path)
+        .pattern(&env.pattern)
+        .build()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path(), r"./src/main.rs");
+    assert_eq!(found[0].relative_path(), r"src/main.rs");
+    assert_eq!(found[0].depth(), 2);
+}
+
 fn get_env_3() -> Vec<String> {
     vec![
         r".\target\release\file.exe".to_string(),
@@ -60,12 +78,12 @@ fn temp_file_find() {
         }
     };
 
-    FindMode::interactive_init(&file, &env);
+    FindMode::interactive_init(&file, &env, &Ignores::load(&env).unwrap(), &[]);
 
     let has_been_found = RefCell::new(false);
 
     loop {
-        let find_result = file.find("Cargo.lock", &|_| {
+        let find_result = file.find("Cargo.toml", &|_| {
             has_been_found.replace(true);
         });
 